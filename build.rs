@@ -0,0 +1,9 @@
+// Generates the messages of `res/stackoverflow.proto` for `--format protobuf`.
+fn main() -> std::io::Result<()> {
+    println!("cargo:rerun-if-changed=res/stackoverflow.proto");
+    prost_build::Config::new()
+        // The protoc bundled with prost-build still calls proto3 `optional`
+        // experimental.
+        .protoc_arg("--experimental_allow_proto3_optional")
+        .compile_protos(&["res/stackoverflow.proto"], &["res"])
+}
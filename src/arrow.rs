@@ -0,0 +1,127 @@
+use crate::formatter::Formatter;
+use crate::output::Output;
+
+use anyhow::{Context, Result};
+use arrow_array::builder::StringBuilder;
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use rio_api::model::{Literal, NamedOrBlankNode, Term, Triple};
+
+use std::sync::Arc;
+
+/// Number of triples per record batch.
+const BATCH_SIZE: usize = 64 * 1024;
+
+/// Writes the triples as an Arrow IPC stream of record batches, with one row
+/// per triple:
+///
+/// - `subject`: the id of the entity, e.g. `p123`;
+/// - `predicate`: e.g. `post.score`;
+/// - `object`: the value of a literal, or the id of another entity;
+/// - `object_kind`: `literal`, `blank` for a blank node such as `u3`, or `iri`
+///   for a named node such as the IRIs of `--base-iri`;
+/// - `datatype`: the datatype IRI of a typed literal, e.g. `xsd:integer`;
+/// - `language`: the language tag of a literal, e.g. `en` with `--lang en`.
+///
+/// A stream has a single schema, so the entities share this generic one
+/// instead of having a column per predicate.
+pub struct ArrowFormatter<W: Output> {
+    writer: StreamWriter<W>,
+    schema: SchemaRef,
+    subjects: StringBuilder,
+    predicates: StringBuilder,
+    objects: StringBuilder,
+    object_kinds: StringBuilder,
+    datatypes: StringBuilder,
+    languages: StringBuilder,
+    rows: usize,
+}
+
+impl<W: Output> ArrowFormatter<W> {
+    pub fn new(write: W) -> Result<Self> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("subject", DataType::Utf8, false),
+            Field::new("predicate", DataType::Utf8, false),
+            Field::new("object", DataType::Utf8, false),
+            Field::new("object_kind", DataType::Utf8, false),
+            Field::new("datatype", DataType::Utf8, true),
+            Field::new("language", DataType::Utf8, true),
+        ]));
+        let writer =
+            StreamWriter::try_new(write, &schema).context("could not write Arrow schema")?;
+        Ok(ArrowFormatter {
+            writer,
+            schema,
+            subjects: StringBuilder::new(),
+            predicates: StringBuilder::new(),
+            objects: StringBuilder::new(),
+            object_kinds: StringBuilder::new(),
+            datatypes: StringBuilder::new(),
+            languages: StringBuilder::new(),
+            rows: 0,
+        })
+    }
+
+    fn flush_batch(&mut self) -> Result<()> {
+        if self.rows == 0 {
+            return Ok(());
+        }
+
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(self.subjects.finish()),
+            Arc::new(self.predicates.finish()),
+            Arc::new(self.objects.finish()),
+            Arc::new(self.object_kinds.finish()),
+            Arc::new(self.datatypes.finish()),
+            Arc::new(self.languages.finish()),
+        ];
+        let batch = RecordBatch::try_new(self.schema.clone(), columns)?;
+        self.writer
+            .write(&batch)
+            .context("could not write Arrow record batch")?;
+        self.rows = 0;
+        Ok(())
+    }
+}
+
+impl<W: Output> Formatter for ArrowFormatter<W> {
+    fn format(&mut self, triple: &Triple) -> Result<()> {
+        let subject = match triple.subject {
+            NamedOrBlankNode::NamedNode(node) => node.iri,
+            NamedOrBlankNode::BlankNode(node) => node.id,
+        };
+        let (object, object_kind, datatype, language) = match triple.object {
+            Term::NamedNode(node) => (node.iri, "iri", None, None),
+            Term::BlankNode(node) => (node.id, "blank", None, None),
+            Term::Literal(Literal::Simple { value }) => (value, "literal", None, None),
+            Term::Literal(Literal::LanguageTaggedString { value, language }) => {
+                (value, "literal", None, Some(language))
+            }
+            Term::Literal(Literal::Typed { value, datatype }) => {
+                (value, "literal", Some(datatype.iri), None)
+            }
+        };
+
+        self.subjects.append_value(subject);
+        self.predicates.append_value(triple.predicate.iri);
+        self.objects.append_value(object);
+        self.object_kinds.append_value(object_kind);
+        self.datatypes.append_option(datatype);
+        self.languages.append_option(language);
+        self.rows += 1;
+        if self.rows == BATCH_SIZE {
+            self.flush_batch()?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.flush_batch()?;
+        self.writer
+            .finish()
+            .context("could not finish Arrow stream")?;
+        self.writer.into_inner()?.finish()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+use crate::warnings::{Category, Warnings};
+
+use std::collections::HashMap;
+
+/// Counts how often pairs of tags appear together on the same post.
+///
+/// Tag names are interned so that each pair costs two `u32`s and a counter.
+/// When the number of distinct pairs grows beyond `max_pairs`, rare pairs are
+/// pruned: the pruning threshold starts at `min_weight` and doubles until at
+/// most half of `max_pairs` remain. A pair that is pruned and seen again starts
+/// counting from zero, so weights are lower bounds once pruning has happened.
+pub struct TagCooccurrence {
+    min_weight: u64,
+    max_pairs: usize,
+    names: Vec<String>,
+    ids: HashMap<String, u32>,
+    pairs: HashMap<(u32, u32), u64>,
+}
+
+impl TagCooccurrence {
+    pub fn new(min_weight: u64, max_pairs: usize) -> Self {
+        TagCooccurrence {
+            min_weight,
+            max_pairs,
+            names: Vec::new(),
+            ids: HashMap::new(),
+            pairs: HashMap::new(),
+        }
+    }
+
+    /// Records every unordered pair of distinct tags in `tags`.
+    pub fn add(&mut self, tags: &[&str], warnings: &mut Warnings) {
+        let mut ids = tags.iter().map(|tag| self.intern(tag)).collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids.dedup();
+
+        for (idx, &a) in ids.iter().enumerate() {
+            for &b in &ids[idx + 1..] {
+                *self.pairs.entry((a, b)).or_insert(0) += 1;
+            }
+        }
+
+        if self.pairs.len() > self.max_pairs {
+            warnings.warn(
+                Category::PrunedTagPairs,
+                format_args!(
+                    "tag co-occurrence: more than {} pairs, pruning rare pairs; weights are now \
+                     approximate",
+                    self.max_pairs
+                ),
+            );
+            self.prune();
+        }
+    }
+
+    /// Returns the pairs whose weight is at least `min_weight`, sorted by tag
+    /// name.
+    pub fn into_pairs(self) -> Vec<(String, String, u64)> {
+        let TagCooccurrence {
+            min_weight,
+            names,
+            pairs,
+            ..
+        } = self;
+
+        let mut pairs = pairs
+            .into_iter()
+            .filter(|&(_, weight)| weight >= min_weight)
+            .map(|((a, b), weight)| {
+                let (a, b) = (&names[a as usize], &names[b as usize]);
+                if a <= b {
+                    (a.clone(), b.clone(), weight)
+                } else {
+                    (b.clone(), a.clone(), weight)
+                }
+            })
+            .collect::<Vec<_>>();
+        pairs.sort_unstable();
+        pairs
+    }
+
+    fn intern(&mut self, tag: &str) -> u32 {
+        if let Some(&id) = self.ids.get(tag) {
+            return id;
+        }
+        let id = self.names.len() as u32;
+        self.names.push(tag.to_string());
+        self.ids.insert(tag.to_string(), id);
+        id
+    }
+
+    fn prune(&mut self) {
+        let mut threshold = self.min_weight.max(2);
+        while self.pairs.len() > self.max_pairs / 2 {
+            self.pairs.retain(|_, weight| *weight >= threshold);
+            threshold = threshold.saturating_mul(2);
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use oxiri::Iri;
+
+pub const XSD_ANY_URI: &str = "http://www.w3.org/2001/XMLSchema#anyURI";
+pub const XSD_BOOLEAN: &str = "http://www.w3.org/2001/XMLSchema#boolean";
+pub const XSD_DATETIME: &str = "http://www.w3.org/2001/XMLSchema#dateTime";
+pub const XSD_GYEAR: &str = "http://www.w3.org/2001/XMLSchema#gYear";
+pub const XSD_INTEGER: &str = "http://www.w3.org/2001/XMLSchema#integer";
+
+/// The year of a valid `xsd:dateTime` as an `xsd:gYear`, e.g. `2008` for
+/// `2008-07-31T21:42:52.667`. Negative years keep their sign.
+pub fn year_of(value: &str) -> Option<&str> {
+    if !is_datetime(value) {
+        return None;
+    }
+    let start = usize::from(value.starts_with('-'));
+    value[start..].find('-').map(|idx| &value[..start + idx])
+}
+
+/// Checks that a value is a valid `xsd:dateTime`, e.g. `2008-07-31T21:42:52.667`
+/// as used by the dump, optionally with a time zone.
+pub fn is_datetime(value: &str) -> bool {
+    let value = value.strip_prefix('-').unwrap_or(value);
+    let (date, time) = match value.split_once('T') {
+        Some(parts) => parts,
+        None => return false,
+    };
+
+    let mut date = date.split('-');
+    let (year, month, day) = match (date.next(), date.next(), date.next(), date.next()) {
+        (Some(year), Some(month), Some(day), None) => (year, month, day),
+        _ => return false,
+    };
+    if year.len() < 4 || !is_number(year, 0, u32::MAX) || !is_field(month, 1, 12) {
+        return false;
+    }
+    if !is_field(day, 1, 31) {
+        return false;
+    }
+
+    let time = match time.strip_suffix('Z') {
+        Some(time) => time,
+        None => match time.rfind(['+', '-']) {
+            Some(idx) => {
+                let (time, zone) = time.split_at(idx);
+                match zone[1..].split_once(':') {
+                    Some((hours, minutes))
+                        if is_field(hours, 0, 14) && is_field(minutes, 0, 59) =>
+                    {
+                        time
+                    }
+                    _ => return false,
+                }
+            }
+            None => time,
+        },
+    };
+    let (time, fraction) = match time.split_once('.') {
+        Some((time, fraction)) => (time, Some(fraction)),
+        None => (time, None),
+    };
+    if let Some(fraction) = fraction {
+        if fraction.is_empty() || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+            return false;
+        }
+    }
+
+    let mut time = time.split(':');
+    match (time.next(), time.next(), time.next(), time.next()) {
+        (Some(hours), Some(minutes), Some(seconds), None) => {
+            is_field(hours, 0, 23) && is_field(minutes, 0, 59) && is_field(seconds, 0, 59)
+                || hours == "24" && minutes == "00" && seconds == "00"
+        }
+        _ => false,
+    }
+}
+
+/// Checks that a value is a valid `xsd:integer`, i.e. digits with an optional
+/// sign.
+pub fn is_integer(value: &str) -> bool {
+    let digits = value.strip_prefix(['+', '-']).unwrap_or(value);
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Checks that a value is an absolute IRI, which a URL such as
+/// `https://example.com/` is, but `example.com` or an empty one is not.
+pub fn is_uri(value: &str) -> bool {
+    Iri::parse(value).is_ok()
+}
+
+/// A two digit field between `min` and `max`.
+fn is_field(value: &str, min: u32, max: u32) -> bool {
+    value.len() == 2 && is_number(value, min, max)
+}
+
+fn is_number(value: &str, min: u32, max: u32) -> bool {
+    value.bytes().all(|b| b.is_ascii_digit())
+        && matches!(value.parse(), Ok(n) if (min..=max).contains(&n))
+}
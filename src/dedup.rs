@@ -0,0 +1,93 @@
+use crate::formatter::Formatter;
+
+use anyhow::Result;
+use clap::ArgEnum;
+use rio_api::model::Triple;
+use xxhash_rust::xxh3::xxh3_128;
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+#[derive(ArgEnum, Clone, Copy)]
+pub enum Dedup {
+    /// Remembers a 128-bit hash of every distinct triple, about 32 bytes
+    /// each: a billion triples need around 32 GB.
+    Exact,
+    /// Uses a Bloom filter of `--dedup-memory` MiB instead. A false positive
+    /// drops a triple that was not written before, which becomes likely once
+    /// there are more than about one distinct triple per 10 bits.
+    Approx,
+}
+
+/// Number of bits that are set in the Bloom filter for every triple, which
+/// keeps false positives around 1% at 10 bits per triple.
+const BLOOM_HASHES: u64 = 7;
+
+/// Skips triples that were already written, for `--dedup`.
+pub struct Deduplicate {
+    inner: Box<dyn Formatter + Send>,
+    seen: Seen,
+    buf: String,
+}
+
+enum Seen {
+    Exact(HashSet<u128>),
+    Approx(Vec<u64>),
+}
+
+impl Deduplicate {
+    /// `memory` is the size of the Bloom filter in MiB with `Dedup::Approx`.
+    pub fn new(inner: Box<dyn Formatter + Send>, dedup: Dedup, memory: u64) -> Self {
+        let seen = match dedup {
+            Dedup::Exact => Seen::Exact(HashSet::new()),
+            Dedup::Approx => Seen::Approx(vec![0; (memory.max(1) << 17) as usize]),
+        };
+        Deduplicate {
+            inner,
+            seen,
+            buf: String::new(),
+        }
+    }
+
+    /// Records the triple, and returns whether it was new.
+    fn insert(&mut self, triple: &Triple) -> bool {
+        self.buf.clear();
+        write!(self.buf, "{}", triple).unwrap();
+        let hash = xxh3_128(self.buf.as_bytes());
+
+        match &mut self.seen {
+            Seen::Exact(hashes) => hashes.insert(hash),
+            Seen::Approx(words) => {
+                // Double hashing: the bits are `h1 + i * h2` for the two
+                // halves of the hash.
+                let bits = words.len() as u64 * 64;
+                let (h1, h2) = (hash as u64, (hash >> 64) as u64 | 1);
+                let mut new = false;
+                for i in 0..BLOOM_HASHES {
+                    let bit = h1.wrapping_add(i.wrapping_mul(h2)) % bits;
+                    let (word, mask) = ((bit / 64) as usize, 1 << (bit % 64));
+                    new |= words[word] & mask == 0;
+                    words[word] |= mask;
+                }
+                new
+            }
+        }
+    }
+}
+
+impl Formatter for Deduplicate {
+    fn format(&mut self, triple: &Triple) -> Result<()> {
+        if self.insert(triple) {
+            self.inner.format(triple)?;
+        }
+        Ok(())
+    }
+
+    fn set_graph(&mut self, entity: &str) {
+        self.inner.set_graph(entity)
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.inner.finish()
+    }
+}
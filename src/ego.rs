@@ -0,0 +1,133 @@
+use crate::id::prefixed;
+use crate::input::{for_each_row, Row};
+use crate::{split_tags, Opts};
+
+use anyhow::Result;
+use log::info;
+
+use std::collections::HashSet;
+
+/// Entities scanned while discovering the ego network, with the kind of node
+/// of their records, whose prefix their ids are given in the output.
+const ENTITIES: &[(&str, &str)] = &[
+    ("Badges", "badge"),
+    ("Comments", "comment"),
+    ("Posts", "post"),
+    ("PostHistory", "posthistory"),
+    ("PostLinks", "postlink"),
+    ("Tags", "tag"),
+    ("Users", "user"),
+    ("Votes", "vote"),
+];
+
+/// Finds the ids of every entity within `depth` hops of the post `seed`.
+///
+/// Each row is a node connected to the entities it references: a post to its
+/// parent, accepted answer, owner, last editor and tags, a comment to its post
+/// and user, and so on. Post links connect their two posts directly, so linked
+/// posts are one hop apart. Tags are included when reached but never expanded,
+/// since a popular tag would otherwise pull in a large part of the site.
+///
+/// Every hop is one pass over all input files, so only the reached ids are kept
+/// in memory.
+pub fn collect_ego(opts: &Opts, seed: u64, depth: u32) -> Result<HashSet<String>> {
+    let seed = prefixed(opts.prefix("post"), &seed.to_string()).to_string();
+    let mut visited = HashSet::new();
+    visited.insert(seed.clone());
+    let mut frontier = HashSet::new();
+    frontier.insert(seed);
+
+    for hop in 1..=depth {
+        let mut next = HashSet::new();
+
+        for &(entity, kind) in ENTITIES {
+            let path = opts.input_path(entity);
+            if opts.skips_missing(&path)? {
+                continue;
+            }
+
+            for_each_row(opts.read_options(), path, |row| {
+                let (id, refs) = match row_links(opts, entity, kind, row)? {
+                    Some(links) => links,
+                    None => return Ok(()),
+                };
+
+                if entity == "PostLinks" {
+                    if refs.iter().any(|r| frontier.contains(r)) {
+                        visited.insert(id);
+                        next.extend(refs.into_iter().filter(|r| !visited.contains(r)));
+                    }
+                    return Ok(());
+                }
+
+                if frontier.contains(&id) && entity != "Tags" {
+                    next.extend(refs.iter().filter(|r| !visited.contains(*r)).cloned());
+                }
+                if !visited.contains(&id)
+                    && refs
+                        .iter()
+                        .any(|r| frontier.contains(r) && !r.starts_with(opts.prefix("tag")))
+                {
+                    next.insert(id);
+                }
+                Ok(())
+            })?;
+        }
+
+        info!("Ego: hop {}: {} new entities", hop, next.len());
+        if next.is_empty() {
+            break;
+        }
+        visited.extend(next.iter().cloned());
+        frontier = next;
+    }
+
+    info!("Ego: {} entities", visited.len());
+    Ok(visited)
+}
+
+/// Returns the id of a row and the ids of the entities it references.
+fn row_links(
+    opts: &Opts,
+    entity: &str,
+    kind: &str,
+    row: &Row,
+) -> Result<Option<(String, Vec<String>)>> {
+    let mut id = None;
+    let mut refs = Vec::new();
+
+    for attribute in row.attributes() {
+        let (key, value) = attribute?;
+        match (entity, key) {
+            ("Tags", b"TagName") => id = Some(opts.tag_id(&value)),
+            ("Tags", b"Id") => (),
+            (_, b"Id") => id = Some(prefixed(opts.prefix(kind), &value).to_string()),
+            ("Badges", b"UserId")
+            | ("Comments", b"UserId")
+            | ("PostHistory", b"UserId")
+            | ("Votes", b"UserId")
+            | ("Posts", b"OwnerUserId")
+            | ("Posts", b"LastEditorUserId") => {
+                refs.push(prefixed(opts.prefix("user"), &value).to_string())
+            }
+            ("Comments", b"PostId")
+            | ("PostHistory", b"PostId")
+            | ("PostLinks", b"PostId")
+            | ("PostLinks", b"RelatedPostId")
+            | ("Votes", b"PostId")
+            | ("Posts", b"ParentId")
+            | ("Posts", b"AcceptedAnswerId")
+            | ("Tags", b"ExcerptPostId")
+            | ("Tags", b"WikiPostId") => {
+                refs.push(prefixed(opts.prefix("post"), &value).to_string())
+            }
+            ("Posts", b"Tags") => {
+                let tags = split_tags(&value).unwrap_or_default();
+                refs.extend(tags.into_iter().map(|tag| opts.tag_id(tag)))
+            }
+            _ => (),
+        }
+    }
+
+    Ok(id.map(|id| (id, refs)))
+}
@@ -0,0 +1,279 @@
+use crate::arrow::ArrowFormatter;
+use crate::jsonld::JsonLdFormatter;
+use crate::output::Output;
+use crate::protobuf::ProtobufFormatter;
+use crate::rdfxml::RdfXml;
+
+use anyhow::Result;
+use clap::ArgEnum;
+use rio_api::formatter::{QuadsFormatter, TriplesFormatter};
+use rio_api::model::{NamedNode, Quad, Term, Triple};
+use rio_turtle::{NQuadsFormatter, NTriplesFormatter, TurtleFormatter};
+
+#[derive(ArgEnum, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Ntriples,
+    /// N-Quads, with the triples of every input file in their own graph, e.g.
+    /// `https://stackoverflow.com/graph#posts`.
+    Nquads,
+    /// Turtle, with the triples of a record grouped under their subject.
+    Turtle,
+    /// RDF/XML, with the predicates in the
+    /// `https://stackoverflow.com/schema#` namespace.
+    Rdfxml,
+    /// A JSON-LD document with a node per entity in its `@graph`.
+    Jsonld,
+    /// Length-delimited protobuf `Record` messages holding a message per
+    /// entity, e.g. a `Post`, see `res/stackoverflow.proto`.
+    Protobuf,
+    /// An Arrow IPC stream with one row per triple, see `src/arrow.rs`.
+    ArrowIpc,
+}
+
+impl OutputFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Ntriples => "nt",
+            OutputFormat::Nquads => "nq",
+            OutputFormat::Turtle => "ttl",
+            OutputFormat::Rdfxml => "rdf",
+            OutputFormat::Jsonld => "jsonld",
+            OutputFormat::Protobuf => "pb",
+            OutputFormat::ArrowIpc => "arrow",
+        }
+    }
+
+    /// Whether outputs of the format can be concatenated into one. RDF/XML
+    /// and JSON-LD outputs are single documents, and an Arrow IPC stream has a
+    /// single schema and ends with a marker.
+    pub fn concatenates(self) -> bool {
+        !matches!(
+            self,
+            OutputFormat::Rdfxml | OutputFormat::Jsonld | OutputFormat::ArrowIpc
+        )
+    }
+}
+
+/// Destination for the triples produced by the `write_*` functions.
+pub trait Formatter {
+    fn format(&mut self, triple: &Triple) -> Result<()>;
+
+    /// Sets the entity whose triples follow, e.g. `Posts`, for formats that
+    /// keep the entities apart.
+    fn set_graph(&mut self, _entity: &str) {}
+
+    /// Writes anything that is still buffered and finishes the output.
+    fn finish(self: Box<Self>) -> Result<()>;
+}
+
+/// Default `--predicate-base`, e.g. `post.score` becomes
+/// `https://stackoverflow.com/schema#post.score`.
+pub const PREDICATE_NAMESPACE: &str = "https://stackoverflow.com/schema#";
+
+/// Creates the formatter of an output format. Relative predicates such as
+/// `post.score` are moved under `predicate_base` in the RDF formats, or left
+/// relative if it is empty. RDF/XML and JSON-LD cannot express relative
+/// predicates, so they fall back to `PREDICATE_NAMESPACE`, while protobuf and
+/// Arrow always keep them as they are.
+pub fn new_formatter<W: Output + Send + 'static>(
+    format: OutputFormat,
+    write: W,
+    predicate_base: &str,
+) -> Result<Box<dyn Formatter + Send>> {
+    let namespace = if predicate_base.is_empty() {
+        PREDICATE_NAMESPACE
+    } else {
+        predicate_base
+    };
+    let formatter: Box<dyn Formatter + Send> = match format {
+        OutputFormat::Ntriples => Box::new(NTriplesFormatter::new(write)),
+        OutputFormat::Nquads => Box::new(NQuads::new(write)),
+        OutputFormat::Turtle => Box::new(TurtleFormatter::new(write)),
+        OutputFormat::Rdfxml => return Ok(Box::new(RdfXml::new(write, namespace)?)),
+        OutputFormat::Jsonld => return Ok(Box::new(JsonLdFormatter::new(write, namespace)?)),
+        OutputFormat::Protobuf => return Ok(Box::new(ProtobufFormatter::new(write))),
+        OutputFormat::ArrowIpc => return Ok(Box::new(ArrowFormatter::new(write)?)),
+    };
+    if predicate_base.is_empty() {
+        return Ok(formatter);
+    }
+    Ok(Box::new(AbsolutePredicates {
+        inner: formatter,
+        base: predicate_base.to_string(),
+        predicate: String::new(),
+        object: String::new(),
+    }))
+}
+
+/// Moves a relative IRI such as `post.score` under `base`, using `buf` for the
+/// result. Full IRIs such as `rdf:type` are left as they are.
+pub fn absolute_iri<'a>(base: &str, iri: &'a str, buf: &'a mut String) -> &'a str {
+    if iri.contains(':') {
+        return iri;
+    }
+    buf.clear();
+    buf.push_str(base);
+    buf.push_str(iri);
+    buf
+}
+
+/// Moves the relative predicates of every triple under `--predicate-base`.
+/// Named node objects are moved too, since they are predicates as well
+/// (e.g. the `rdf:predicate` of a reified statement).
+struct AbsolutePredicates {
+    inner: Box<dyn Formatter + Send>,
+    base: String,
+    predicate: String,
+    object: String,
+}
+
+impl Formatter for AbsolutePredicates {
+    fn format(&mut self, triple: &Triple) -> Result<()> {
+        let predicate = absolute_iri(&self.base, triple.predicate.iri, &mut self.predicate);
+        let object = match triple.object {
+            Term::NamedNode(node) => NamedNode {
+                iri: absolute_iri(&self.base, node.iri, &mut self.object),
+            }
+            .into(),
+            object => object,
+        };
+        self.inner.format(&Triple {
+            subject: triple.subject,
+            predicate: NamedNode { iri: predicate },
+            object,
+        })
+    }
+
+    fn set_graph(&mut self, entity: &str) {
+        self.inner.set_graph(entity)
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.inner.finish()
+    }
+}
+
+impl<W: Output> Formatter for NTriplesFormatter<W> {
+    fn format(&mut self, triple: &Triple) -> Result<()> {
+        TriplesFormatter::format(self, triple)?;
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        NTriplesFormatter::finish(*self).finish()?;
+        Ok(())
+    }
+}
+
+/// Namespace of the graphs written with `--format nquads`.
+const GRAPH_NAMESPACE: &str = "https://stackoverflow.com/graph#";
+
+/// Writes N-Quads, with every triple in the graph of the entity set last.
+pub struct NQuads<W: Output> {
+    inner: NQuadsFormatter<W>,
+    graph: String,
+}
+
+impl<W: Output> NQuads<W> {
+    pub fn new(write: W) -> Self {
+        NQuads {
+            inner: NQuadsFormatter::new(write),
+            graph: String::new(),
+        }
+    }
+}
+
+impl<W: Output> Formatter for NQuads<W> {
+    fn format(&mut self, triple: &Triple) -> Result<()> {
+        let graph_name = if self.graph.is_empty() {
+            None
+        } else {
+            Some(NamedNode { iri: &self.graph }.into())
+        };
+        self.inner.format(&Quad {
+            subject: triple.subject,
+            predicate: triple.predicate,
+            object: triple.object,
+            graph_name,
+        })?;
+        Ok(())
+    }
+
+    fn set_graph(&mut self, entity: &str) {
+        self.graph.clear();
+        self.graph.push_str(GRAPH_NAMESPACE);
+        self.graph.push_str(&entity.to_ascii_lowercase());
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.inner.finish().finish()?;
+        Ok(())
+    }
+}
+
+impl<W: Output> Formatter for TurtleFormatter<W> {
+    fn format(&mut self, triple: &Triple) -> Result<()> {
+        TriplesFormatter::format(self, triple)?;
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        TurtleFormatter::finish(*self)?.finish()?;
+        Ok(())
+    }
+}
+
+/// Writes every triple to a primary formatter and to any number of secondary
+/// ones.
+pub struct Tee<'a> {
+    pub primary: &'a mut dyn Formatter,
+    pub secondary: Vec<&'a mut dyn Formatter>,
+}
+
+impl Formatter for Tee<'_> {
+    fn format(&mut self, triple: &Triple) -> Result<()> {
+        self.primary.format(triple)?;
+        for formatter in &mut self.secondary {
+            formatter.format(triple)?;
+        }
+        Ok(())
+    }
+
+    /// The formatters of a `Tee` are borrowed, so they are finished by their
+    /// owners instead.
+    fn finish(self: Box<Self>) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Counts the triples written through it, and whether writing one of them
+/// failed, which tells errors of the output apart from those of a record.
+pub struct Counter<'a> {
+    inner: &'a mut dyn Formatter,
+    pub count: u64,
+    pub failed: bool,
+}
+
+impl<'a> Counter<'a> {
+    pub fn new(inner: &'a mut dyn Formatter) -> Self {
+        Counter {
+            inner,
+            count: 0,
+            failed: false,
+        }
+    }
+}
+
+impl Formatter for Counter<'_> {
+    fn format(&mut self, triple: &Triple) -> Result<()> {
+        self.count += 1;
+        let result = self.inner.format(triple);
+        self.failed |= result.is_err();
+        result
+    }
+
+    /// The inner formatter is borrowed, so it is finished by its owner.
+    fn finish(self: Box<Self>) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+use clap::ArgEnum;
+use data_encoding::HEXLOWER;
+use sha2::{Digest, Sha256};
+use xxhash_rust::xxh3::Xxh3;
+
+#[derive(ArgEnum, Clone, Copy)]
+pub enum HashAlgorithm {
+    Sha256,
+    Xxh3,
+}
+
+/// Hashes the normalized title and body of a post, so that the same post can
+/// be recognized across sites and dump versions.
+///
+/// Both fields have surrounding whitespace trimmed and `\r\n` line endings
+/// converted to `\n`, and are then joined with a single `\n`. Answers have an
+/// empty title. Case and HTML markup are preserved. The hash is returned as
+/// lowercase hex: 64 characters for SHA-256, 16 for XXH3 (64-bit).
+pub fn content_hash(algorithm: HashAlgorithm, title: &str, body: &str) -> String {
+    let title = normalize(title);
+    let body = normalize(body);
+    let parts = [title.as_bytes(), b"\n", body.as_bytes()];
+
+    match algorithm {
+        HashAlgorithm::Sha256 => {
+            let mut hasher = Sha256::new();
+            parts.iter().for_each(|part| hasher.update(part));
+            HEXLOWER.encode(&hasher.finalize())
+        }
+        HashAlgorithm::Xxh3 => {
+            let mut hasher = Xxh3::new();
+            parts.iter().for_each(|part| hasher.update(part));
+            HEXLOWER.encode(&hasher.digest().to_be_bytes())
+        }
+    }
+}
+
+/// Pseudonym for a user under `--anonymize`: the first 16 hex characters of
+/// the SHA-256 of the user id, so the same user always gets the same name.
+pub fn user_pseudonym(user_id: &str) -> String {
+    let hash = Sha256::digest(user_id.as_bytes());
+    HEXLOWER.encode(&hash[..8])
+}
+
+/// Id of the placeholder user for `--anonymous-owners`: the user prefix and an
+/// `a`, e.g. `ua`, followed by the first 16 hex characters of the SHA-256 of a
+/// display name. Real user ids are numbers, so they never collide with it.
+pub fn anonymous_user_id(prefix: &str, display_name: &str) -> String {
+    let hash = Sha256::digest(display_name.as_bytes());
+    format!("{}a{}", prefix, HEXLOWER.encode(&hash[..8]))
+}
+
+fn normalize(text: &str) -> String {
+    text.trim().replace("\r\n", "\n")
+}
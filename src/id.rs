@@ -0,0 +1,126 @@
+use anyhow::{bail, Context, Result};
+
+use std::fmt::{self, Display};
+use std::ops::Deref;
+use std::str;
+
+/// Longest id that is kept inline, which fits every numeric id with a
+/// single-letter prefix.
+const INLINE_LEN: usize = 23;
+
+/// The kinds of nodes, with the default prefix of their ids, which `--prefix`
+/// overrides. The kinds also name the segments of the IRIs of `--base-iri`.
+pub const PREFIXES: &[(&str, &str)] = &[
+    ("badge", "b"),
+    ("comment", "c"),
+    ("cooccurrence", "o"),
+    ("post", "p"),
+    ("posthistory", "h"),
+    ("postlink", "l"),
+    ("statement", "r"),
+    ("tag", "t"),
+    ("user", "u"),
+    ("vote", "v"),
+];
+
+/// Parses a `--prefix`, e.g. `post=post_`. Prefixes start with a letter and
+/// consist of letters, digits, `_` and `-`, so that they are valid in blank
+/// node labels.
+pub fn parse_prefix(s: &str) -> Result<(String, String)> {
+    let (kind, prefix) = s
+        .split_once('=')
+        .context("expected a value of the form `entity=prefix`")?;
+    if !PREFIXES.iter().any(|&(known, _)| known == kind) {
+        let kinds = PREFIXES.iter().map(|&(kind, _)| kind).collect::<Vec<_>>();
+        bail!(
+            "unknown entity `{}`, expected one of: {}",
+            kind,
+            kinds.join(", ")
+        );
+    }
+    let mut chars = prefix.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid {
+        bail!("expected a prefix of letters, digits, `_` and `-` that starts with a letter");
+    }
+    Ok((kind.to_string(), prefix.to_string()))
+}
+
+/// Rejects prefixes with which the ids of one kind could be taken for those of
+/// another: no prefix may start with another one, e.g. `p` and `po`, or be the
+/// start of the `stats` node of `--output-stats-only`.
+pub fn check_prefixes(prefixes: &[(&str, &str)]) -> Result<()> {
+    for (i, &(kind, prefix)) in prefixes.iter().enumerate() {
+        if "stats".starts_with(prefix) {
+            bail!(
+                "prefix `{}` of {} ids is ambiguous with the `stats` node",
+                prefix,
+                kind
+            );
+        }
+        for &(other_kind, other) in &prefixes[i + 1..] {
+            if prefix.starts_with(other) || other.starts_with(prefix) {
+                bail!(
+                    "prefixes `{}` of {} ids and `{}` of {} ids are ambiguous, since one \
+                     starts with the other",
+                    prefix,
+                    kind,
+                    other,
+                    other_kind
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The id of a record with the prefix of its entity, e.g. `p123` for post 123.
+/// Ids are built for every reference of every record, so they are kept inline
+/// instead of being allocated, unless they are unusually long.
+pub struct PrefixedId(Repr);
+
+// Private, so that only `prefixed` can build an inline id, whose bytes must be
+// valid UTF-8.
+enum Repr {
+    Inline { buf: [u8; INLINE_LEN], len: u8 },
+    Heap(String),
+}
+
+pub fn prefixed(prefix: &str, id: &str) -> PrefixedId {
+    let len = prefix.len() + id.len();
+    if len > INLINE_LEN {
+        let mut heap = String::with_capacity(len);
+        heap.push_str(prefix);
+        heap.push_str(id);
+        return PrefixedId(Repr::Heap(heap));
+    }
+
+    let mut buf = [0; INLINE_LEN];
+    let (head, tail) = buf.split_at_mut(prefix.len());
+    head.copy_from_slice(prefix.as_bytes());
+    tail[..id.len()].copy_from_slice(id.as_bytes());
+    PrefixedId(Repr::Inline {
+        buf,
+        len: len as u8,
+    })
+}
+
+impl Deref for PrefixedId {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match &self.0 {
+            // SAFETY: `prefixed` copies two `str`s into the first `len` bytes,
+            // and a concatenation of `str`s is valid UTF-8.
+            Repr::Inline { buf, len } => unsafe { str::from_utf8_unchecked(&buf[..*len as usize]) },
+            Repr::Heap(heap) => heap,
+        }
+    }
+}
+
+impl Display for PrefixedId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self)
+    }
+}
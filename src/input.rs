@@ -0,0 +1,846 @@
+use crate::stop;
+
+use anyhow::{bail, Context, Result};
+use clap::ArgEnum;
+use csv::StringRecord;
+use encoding_rs::{Encoding, UTF_8};
+use flate2::bufread::{GzDecoder, MultiGzDecoder};
+use indicatif::ProgressBar;
+use log::{debug, info, warn};
+use memmap2::Mmap;
+use quick_xml::escape::unescape;
+use quick_xml::events::attributes::{Attribute, Attributes as XmlAttributes};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use sevenz_rust::{Password, SevenZReader};
+use zip::ZipArchive;
+
+use std::borrow::Cow;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Cursor, Read};
+use std::iter::Zip;
+use std::path::{Path, PathBuf};
+use std::str;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread;
+
+#[derive(ArgEnum, Clone, Copy, PartialEq)]
+pub enum InputFormat {
+    /// The `<row .../>` XML files from the Stack Exchange data dump.
+    Xml,
+    /// CSV files exported from the Stack Exchange Data Explorer, with a header
+    /// whose column names match the dump attributes (`Id`, `PostTypeId`, ...).
+    Csv,
+}
+
+impl InputFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            InputFormat::Xml => "xml",
+            InputFormat::Csv => "csv",
+        }
+    }
+}
+
+#[derive(ArgEnum, Clone, Copy, PartialEq)]
+pub enum OnError {
+    /// Skips records that cannot be parsed, with a warning.
+    Skip,
+    /// Fails on the first record that cannot be parsed.
+    Abort,
+}
+
+/// How input files are read, shared by every pass over them.
+#[derive(Clone, Copy)]
+pub struct ReadOptions {
+    pub format: InputFormat,
+    pub on_error: OnError,
+    /// Memory-maps plain files instead of reading them into a buffer.
+    pub mmap: bool,
+    /// Initial size in bytes of the read buffer, and of the buffer that holds
+    /// an element while it is parsed.
+    pub buffer_size: usize,
+    /// Fails on CSV files with a repeated column instead of using its last
+    /// value. Repeated XML attributes are always malformed, and this makes
+    /// them fail with their position in the file.
+    pub strict_attrs: bool,
+    /// Stops after this many records of an input, across all of its shards.
+    pub limit: Option<u64>,
+    /// Encoding of the XML files, instead of the one in their declaration.
+    pub encoding: Option<&'static Encoding>,
+}
+
+impl ReadOptions {
+    fn reached(&self, rows: u64) -> bool {
+        self.limit.is_some_and(|limit| rows >= limit)
+    }
+}
+
+/// A single record of an entity, i.e. a `<row/>` element or a CSV line.
+pub enum Row<'a> {
+    Xml {
+        element: &'a BytesStart<'a>,
+        /// Position in the file right after the element.
+        position: u64,
+        /// Encoding of the attribute values.
+        encoding: &'static Encoding,
+    },
+    Csv {
+        headers: &'a StringRecord,
+        record: &'a StringRecord,
+    },
+}
+
+impl<'a> Row<'a> {
+    /// Iterates over the `(name, value)` pairs of the record. Empty CSV fields
+    /// are treated as missing attributes, since that is how NULLs are exported.
+    pub fn attributes(&self) -> Attributes<'_> {
+        match self {
+            Row::Xml {
+                element, encoding, ..
+            } => Attributes::Xml(element.attributes(), encoding),
+            Row::Csv { headers, record } => Attributes::Csv(headers.iter().zip(record.iter())),
+        }
+    }
+
+    /// Byte position of the record in its file, for pointing at it in
+    /// messages: after an XML element, or at the start of a CSV line.
+    pub fn position(&self) -> u64 {
+        match self {
+            Row::Xml { position, .. } => *position,
+            Row::Csv { record, .. } => record.position().map_or(0, |position| position.byte()),
+        }
+    }
+}
+
+/// A record that is kept after it was read, so that it can be converted on
+/// another thread with `--parallel records`.
+pub enum OwnedRow {
+    Xml {
+        element: BytesStart<'static>,
+        position: u64,
+        encoding: &'static Encoding,
+    },
+    Csv {
+        headers: StringRecord,
+        record: StringRecord,
+    },
+}
+
+impl Row<'_> {
+    pub fn to_owned(&self) -> OwnedRow {
+        match *self {
+            Row::Xml {
+                element,
+                position,
+                encoding,
+            } => OwnedRow::Xml {
+                element: element.to_owned(),
+                position,
+                encoding,
+            },
+            Row::Csv { headers, record } => OwnedRow::Csv {
+                headers: headers.clone(),
+                record: record.clone(),
+            },
+        }
+    }
+}
+
+impl OwnedRow {
+    pub fn row(&self) -> Row<'_> {
+        match self {
+            OwnedRow::Xml {
+                element,
+                position,
+                encoding,
+            } => Row::Xml {
+                element,
+                position: *position,
+                encoding,
+            },
+            OwnedRow::Csv { headers, record } => Row::Csv { headers, record },
+        }
+    }
+}
+
+pub enum Attributes<'a> {
+    Xml(XmlAttributes<'a>, &'static Encoding),
+    Csv(Zip<csv::StringRecordIter<'a>, csv::StringRecordIter<'a>>),
+}
+
+impl<'a> Iterator for Attributes<'a> {
+    type Item = Result<(&'a [u8], Cow<'a, str>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Attributes::Xml(attributes, encoding) => {
+                let attribute = attributes.next()?;
+                Some(
+                    attribute
+                        .context("could not parse attribute")
+                        .and_then(|attribute| {
+                            let key = attribute.key;
+                            Ok((key, parse_attribute(attribute, encoding)?))
+                        }),
+                )
+            }
+            Attributes::Csv(fields) => loop {
+                let (key, value) = fields.next()?;
+                if !value.is_empty() {
+                    return Some(Ok((key.as_bytes(), Cow::Borrowed(value))));
+                }
+            },
+        }
+    }
+}
+
+/// Calls `f` on every record of the given file, until a stop is requested or
+/// the limit of the options is reached. Returns the number of records that
+/// were skipped with `OnError::Skip`, which are logged as they happen.
+pub fn for_each_row<P: AsRef<Path>>(
+    read: ReadOptions,
+    path: P,
+    f: impl FnMut(&Row) -> Result<()>,
+) -> Result<u64> {
+    for_each_tracked_row(read, path, None, f)
+}
+
+/// Like `for_each_row`, but also advances `bar` by the bytes read from the
+/// file.
+pub fn for_each_tracked_row<P: AsRef<Path>>(
+    read: ReadOptions,
+    path: P,
+    bar: Option<&ProgressBar>,
+    mut f: impl FnMut(&Row) -> Result<()>,
+) -> Result<u64> {
+    let mut skipped = 0;
+    let mut rows = 0;
+    for path in input_files(path.as_ref())? {
+        if read.reached(rows) {
+            break;
+        }
+        debug!("reading {}", path.display());
+        let input = open_input(&path, read, bar)?;
+        skipped += match read.format {
+            InputFormat::Xml => for_each_xml_row(&path, input, read, &mut rows, &mut f)?,
+            InputFormat::Csv => for_each_csv_row(&path, input, read, &mut rows, &mut f)?,
+        };
+    }
+    Ok(skipped)
+}
+
+/// The files that make up an input: the file itself, followed by its shards
+/// in numeric order, e.g. `Posts.xml`, `Posts.1.xml`, `Posts.2.xml`, ... for
+/// `Posts.xml`. Each of them may also be gzipped, e.g. `Posts.xml.gz`, in
+/// which case the plain file is preferred if both exist. Sharded and gzipped
+/// inputs are only looked for in directories, not in archives. When there is
+/// neither, the file itself is returned, so that opening it fails as usual.
+pub fn input_files(path: &Path) -> Result<Vec<PathBuf>> {
+    let dir = match path.parent() {
+        Some(dir) if dir.is_dir() => dir,
+        _ => return Ok(vec![path.to_path_buf()]),
+    };
+    let (stem, extension) = match (path.file_stem(), path.extension()) {
+        (Some(stem), Some(extension)) => (stem.to_string_lossy(), extension.to_string_lossy()),
+        _ => return Ok(vec![path.to_path_buf()]),
+    };
+    let prefix = format!("{}.", stem);
+    let suffix = format!(".{}", extension);
+    let gz_suffix = format!(".{}.gz", extension);
+
+    let mut shards = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("could not read {}", dir.display()))? {
+        let entry = entry.with_context(|| format!("could not read {}", dir.display()))?;
+        let name = entry.file_name();
+        let shard = name
+            .to_str()
+            .and_then(|name| name.strip_prefix(&prefix))
+            .and_then(|name| {
+                name.strip_suffix(&gz_suffix)
+                    .or_else(|| name.strip_suffix(&suffix))
+            })
+            .and_then(|number| number.parse::<u64>().ok());
+        if let Some(shard) = shard {
+            shards.push((shard, entry.path()));
+        }
+    }
+    // `Posts.1.xml` sorts before `Posts.1.xml.gz`, which is dropped.
+    shards.sort_unstable();
+    shards.dedup_by_key(|&mut (shard, _)| shard);
+
+    let gz = with_gz_extension(path);
+    let path = if !path.exists() && gz.exists() {
+        &gz
+    } else {
+        path
+    };
+    let mut files = Vec::with_capacity(shards.len() + 1);
+    if shards.is_empty() || path.exists() {
+        files.push(path.to_path_buf());
+    }
+    files.extend(shards.into_iter().map(|(_, path)| path));
+    Ok(files)
+}
+
+fn with_gz_extension(path: &Path) -> PathBuf {
+    let mut path = path.to_path_buf().into_os_string();
+    path.push(".gz");
+    PathBuf::from(path)
+}
+
+fn is_gzipped(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "gz")
+}
+
+/// Total size in bytes of the files of an input, or `None` if it is not known,
+/// e.g. for files in an archive. Gzipped files count with their compressed
+/// size, which is also what their progress is measured in.
+pub fn input_size(path: &Path) -> Option<u64> {
+    let files = input_files(path).ok()?;
+    files
+        .iter()
+        .map(|path| {
+            fs::metadata(path)
+                .ok()
+                .filter(|metadata| metadata.is_file())
+        })
+        .map(|metadata| metadata.map(|metadata| metadata.len()))
+        .sum()
+}
+
+fn for_each_xml_row(
+    path: &Path,
+    input: Box<dyn BufRead>,
+    read: ReadOptions,
+    rows: &mut u64,
+    f: &mut impl FnMut(&Row) -> Result<()>,
+) -> Result<u64> {
+    let on_error = read.on_error;
+    let mut buf = Vec::with_capacity(read.buffer_size);
+    let mut skipped = 0;
+    // Position of the current reader in the file, see below.
+    let mut offset = 0;
+    let mut error_position = None;
+    let mut encoding = read.encoding.unwrap_or(UTF_8);
+    if encoding != UTF_8 {
+        info!("{}: decoding as {}", path.display(), encoding.name());
+    }
+
+    let mut reader = Reader::from_reader(input);
+    reader.trim_text(true);
+
+    while !stop::requested() && !read.reached(*rows) {
+        match reader.read_event(&mut buf) {
+            // The declaration comes first, so it applies to every record.
+            Ok(Event::Decl(e)) if read.encoding.is_none() => {
+                if let Some(label) = e.encoding() {
+                    let label = label.with_context(|| {
+                        format!("malformed XML declaration in {}", path.display())
+                    })?;
+                    encoding = match Encoding::for_label(&label) {
+                        Some(encoding) if encoding.is_ascii_compatible() => encoding,
+                        _ => bail!(
+                            "unsupported encoding `{}` in the XML declaration of {}, see \
+                             --encoding",
+                            String::from_utf8_lossy(&label),
+                            path.display()
+                        ),
+                    };
+                    if encoding != UTF_8 {
+                        info!("{}: decoding as {}", path.display(), encoding.name());
+                    }
+                }
+            }
+            Ok(Event::Empty(e)) => {
+                // Records with malformed attributes are skipped before the
+                // writers see them, since their errors abort. With
+                // `--strict-attrs` they abort here, where the position in the
+                // file is known.
+                let error = match on_error {
+                    OnError::Abort if !read.strict_attrs => None,
+                    _ => e.attributes().find_map(|attribute| attribute.err()),
+                };
+                match error {
+                    Some(error) if on_error == OnError::Abort => bail!(
+                        "malformed XML in {} at position {}: {}",
+                        path.display(),
+                        offset + reader.buffer_position(),
+                        error
+                    ),
+                    Some(error) => {
+                        warn!(
+                            "malformed XML in {} at position {}: {}, skipping",
+                            path.display(),
+                            offset + reader.buffer_position(),
+                            error
+                        );
+                        skipped += 1;
+                    }
+                    None => {
+                        f(&Row::Xml {
+                            element: &e,
+                            position: (offset + reader.buffer_position()) as u64,
+                            encoding,
+                        })?;
+                        *rows += 1;
+                    }
+                }
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => (),
+            Err(e) => {
+                let position = offset + reader.buffer_position();
+                let error = anyhow::Error::new(e).context(format!(
+                    "malformed XML in {} at position {}",
+                    path.display(),
+                    position
+                ));
+                if on_error == OnError::Abort {
+                    return Err(error);
+                }
+                // A reader that does not get past an error, e.g. at the end
+                // of a truncated file, would fail forever.
+                if error_position == Some(position) {
+                    break;
+                }
+                warn!("{:#}, skipping", error);
+                error_position = Some(position);
+                skipped += 1;
+
+                // The reader only returns `Eof` after an error, so reading
+                // goes on with a new one. Records are self-closing elements,
+                // so the end tags of the enclosing element are not checked.
+                offset = position;
+                reader = Reader::from_reader(reader.into_underlying_reader());
+                reader.trim_text(true).check_end_names(false);
+            }
+        }
+        buf.clear();
+    }
+
+    Ok(skipped)
+}
+
+fn for_each_csv_row(
+    path: &Path,
+    input: Box<dyn BufRead>,
+    read: ReadOptions,
+    rows: &mut u64,
+    f: &mut impl FnMut(&Row) -> Result<()>,
+) -> Result<u64> {
+    let on_error = read.on_error;
+    let mut reader = csv::ReaderBuilder::new()
+        .buffer_capacity(read.buffer_size)
+        .from_reader(input);
+    let mut skipped = 0;
+
+    // Exports from Windows tools often start with a byte order mark, which
+    // would otherwise end up as part of the first column name.
+    let headers = reader
+        .headers()
+        .context("could not read CSV header")?
+        .iter()
+        .map(|header| header.trim_start_matches('\u{feff}').trim())
+        .collect::<StringRecord>();
+    if read.strict_attrs {
+        for (idx, header) in headers.iter().enumerate() {
+            if headers.iter().take(idx).any(|previous| previous == header) {
+                bail!(
+                    "repeated column {:?} in the CSV header of {}",
+                    header,
+                    path.display()
+                );
+            }
+        }
+    }
+
+    let mut record = StringRecord::new();
+    while !stop::requested() && !read.reached(*rows) {
+        match reader.read_record(&mut record) {
+            Ok(true) => {
+                f(&Row::Csv {
+                    headers: &headers,
+                    record: &record,
+                })?;
+                *rows += 1;
+            }
+            Ok(false) => break,
+            // Errors that the reader cannot get past are I/O errors.
+            Err(e) if on_error == OnError::Skip && !e.is_io_error() => {
+                warn!(
+                    "malformed CSV record in {}: {}, skipping",
+                    path.display(),
+                    e
+                );
+                skipped += 1;
+            }
+            Err(e) => return Err(e).context("could not parse CSV record"),
+        }
+    }
+
+    Ok(skipped)
+}
+
+/// The path that reads from stdin instead of a file.
+pub fn is_stdin(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+/// Opens an input file, or stdin for `-`. When its directory is actually an
+/// archive, the file is read from the archive instead: the archive is scanned
+/// for the first member with the same file name, in any directory. Files that
+/// end with `.gz` are decompressed while they are read. With `--mmap`, other
+/// files are memory-mapped if possible. `bar` is advanced by the bytes read
+/// from the file, before they are buffered.
+fn open_input(
+    path: &Path,
+    read: ReadOptions,
+    bar: Option<&ProgressBar>,
+) -> Result<Box<dyn BufRead>> {
+    let capacity = read.buffer_size;
+    let track = |read: Box<dyn Read>| -> Box<dyn Read> {
+        match bar {
+            Some(bar) => Box::new(bar.wrap_read(read)),
+            None => read,
+        }
+    };
+
+    if is_stdin(path) {
+        let stdin = track(Box::new(io::stdin()));
+        return Ok(Box::new(BufReader::with_capacity(capacity, stdin)));
+    }
+
+    let archive = match path.parent() {
+        Some(archive) if archive.is_file() => archive,
+        _ => {
+            let file =
+                File::open(path).with_context(|| format!("could not open {}", path.display()))?;
+            if is_gzipped(path) {
+                // Concatenated members, e.g. of `pigz`, are read as one stream.
+                let file = BufReader::with_capacity(capacity, track(Box::new(file)));
+                let decoder = MultiGzDecoder::new(file);
+                return Ok(Box::new(BufReader::with_capacity(capacity, decoder)));
+            }
+            if read.mmap {
+                // Safety: the mapping is only valid as long as nobody else
+                // truncates or modifies the file while it is read.
+                match unsafe { Mmap::map(&file) } {
+                    Ok(map) => {
+                        let bar = bar.cloned();
+                        return Ok(Box::new(MappedInput {
+                            input: Cursor::new(map),
+                            bar,
+                        }));
+                    }
+                    Err(e) => warn!(
+                        "could not memory-map {}: {}, reading it instead",
+                        path.display(),
+                        e
+                    ),
+                }
+            }
+            return Ok(Box::new(BufReader::with_capacity(
+                capacity,
+                track(Box::new(file)),
+            )));
+        }
+    };
+
+    let member = match ArchiveKind::of(archive) {
+        ArchiveKind::Tar => find_member(archive, path)?.map(|member| Box::new(member) as _),
+        kind => extract_member(kind, archive, path)?,
+    };
+    let member: Box<dyn Read> = member.with_context(|| {
+        format!(
+            "{} not found in {}",
+            path.file_name().unwrap_or_default().to_string_lossy(),
+            archive_name(path)
+        )
+    })?;
+    Ok(Box::new(BufReader::with_capacity(capacity, track(member))))
+}
+
+/// A memory-mapped input file, which is parsed straight from the mapping
+/// instead of being copied into a buffer first.
+struct MappedInput {
+    input: Cursor<Mmap>,
+    bar: Option<ProgressBar>,
+}
+
+impl Read for MappedInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.input.read(buf)?;
+        if let Some(bar) = &self.bar {
+            bar.inc(len as u64);
+        }
+        Ok(len)
+    }
+}
+
+impl BufRead for MappedInput {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.input.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.input.consume(amt);
+        if let Some(bar) = &self.bar {
+            bar.inc(amt as u64);
+        }
+    }
+}
+
+/// Whether an input file or any of its shards exists, also when it is in a
+/// archive. Errors other than the file not being found are returned.
+pub fn input_exists(path: &Path) -> Result<bool> {
+    if is_stdin(path) {
+        return Ok(true);
+    }
+    match path.parent() {
+        Some(archive) if archive.is_file() => match ArchiveKind::of(archive) {
+            ArchiveKind::Tar => Ok(find_member(archive, path)?.is_some()),
+            kind => Ok(member_index(kind, archive, path)?.is_some()),
+        },
+        _ => match fs::metadata(&input_files(path)?[0]) {
+            Ok(_) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e).with_context(|| format!("could not open {}", path.display())),
+        },
+    }
+}
+
+/// Scans a tar archive for the member with the file name of `path`, and
+/// returns its contents.
+fn find_member(archive: &Path, path: &Path) -> Result<Option<impl Read>> {
+    let name = path.file_name().context("input path has no file name")?;
+
+    let mut archive = tar::Archive::new(open_archive(archive)?);
+    let mut size = None;
+    for entry in archive.entries().context("could not read tar archive")? {
+        let entry = entry.context("could not read tar archive")?;
+        if entry.path()?.file_name() == Some(name) {
+            size = Some(entry.size());
+            break;
+        }
+    }
+
+    // The entries are read lazily, so the archive is still positioned at the
+    // contents of the member that was found.
+    Ok(size.map(|size| archive.into_inner().take(size)))
+}
+
+/// Opens a tar archive, decompressing it if it starts with the gzip magic
+/// bytes.
+fn open_archive(path: &Path) -> Result<Box<dyn Read>> {
+    let file = File::open(path).with_context(|| format!("could not open {}", path.display()))?;
+    let mut reader = BufReader::new(file);
+    if reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+        Ok(Box::new(GzDecoder::new(reader)))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+/// The format of an archive, by its extension. Anything but `.7z` and `.zip`
+/// is read as a tar archive.
+#[derive(Clone, Copy)]
+enum ArchiveKind {
+    Tar,
+    SevenZ,
+    Zip,
+}
+
+impl ArchiveKind {
+    fn of(archive: &Path) -> Self {
+        match archive.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("7z") => ArchiveKind::SevenZ,
+            Some(extension) if extension.eq_ignore_ascii_case("zip") => ArchiveKind::Zip,
+            _ => ArchiveKind::Tar,
+        }
+    }
+}
+
+/// Looks up the member with the file name of `path` in the index of a 7z or
+/// zip archive, without decompressing anything.
+fn member_index(kind: ArchiveKind, archive: &Path, path: &Path) -> Result<Option<usize>> {
+    let name = path.file_name().context("input path has no file name")?;
+    let matches = |member: &str| Path::new(member).file_name() == Some(name);
+    let context = || format!("could not read {}", archive.display());
+
+    match kind {
+        ArchiveKind::SevenZ => {
+            let reader = SevenZReader::open(archive, Password::empty()).with_context(context)?;
+            let files = &reader.archive().files;
+            Ok(files
+                .iter()
+                .position(|file| !file.is_directory() && matches(file.name())))
+        }
+        ArchiveKind::Zip => {
+            let file = File::open(archive).with_context(context)?;
+            let zip = ZipArchive::new(BufReader::new(file)).with_context(context)?;
+            let index = zip.file_names().position(matches);
+            Ok(index)
+        }
+        ArchiveKind::Tar => unreachable!("tar archives have no index"),
+    }
+}
+
+/// Streams a member of a 7z or zip archive. Both libraries only lend out the
+/// contents of a member while the archive is borrowed, so the member is
+/// decompressed on a separate thread, which stops early when the returned
+/// reader is dropped.
+fn extract_member(kind: ArchiveKind, archive: &Path, path: &Path) -> Result<Option<Box<dyn Read>>> {
+    let index = match member_index(kind, archive, path)? {
+        Some(index) => index,
+        None => return Ok(None),
+    };
+    let name = path.file_name().map(OsString::from);
+    let archive = archive.to_path_buf();
+    let (sender, receiver) = mpsc::sync_channel(CHUNKS_IN_FLIGHT);
+
+    thread::spawn(move || {
+        let result = match kind {
+            ArchiveKind::SevenZ => extract_7z(&archive, name, &sender),
+            _ => extract_zip(&archive, index, &sender),
+        };
+        if let Err(e) = result {
+            let e = io::Error::other(format!("{:#}", e));
+            let _ = sender.send(Err(e));
+        }
+    });
+    Ok(Some(Box::new(ChunkReader {
+        receiver,
+        chunk: Vec::new(),
+        pos: 0,
+    })))
+}
+
+/// Chunks of a member that may be decompressed ahead of the reader.
+const CHUNKS_IN_FLIGHT: usize = 16;
+const CHUNK_SIZE: usize = 1 << 16;
+
+type Chunks = SyncSender<io::Result<Vec<u8>>>;
+
+fn extract_7z(archive: &Path, name: Option<OsString>, sender: &Chunks) -> Result<()> {
+    let mut reader = SevenZReader::open(archive, Password::empty())
+        .with_context(|| format!("could not read {}", archive.display()))?;
+    reader
+        .for_each_entries(|entry, read| {
+            if entry.is_directory() || Path::new(entry.name()).file_name() != name.as_deref() {
+                // Members of a solid archive share one compressed stream, so
+                // the ones before the member are decompressed and discarded.
+                io::copy(read, &mut io::sink())?;
+                return Ok(true);
+            }
+            send_chunks(read, sender)?;
+            Ok(false)
+        })
+        .with_context(|| format!("could not read {}", archive.display()))
+}
+
+fn extract_zip(archive: &Path, index: usize, sender: &Chunks) -> Result<()> {
+    let context = || format!("could not read {}", archive.display());
+    let file = File::open(archive).with_context(context)?;
+    let mut zip = ZipArchive::new(BufReader::new(file)).with_context(context)?;
+    let mut member = zip.by_index(index).with_context(context)?;
+    send_chunks(&mut member, sender).with_context(context)
+}
+
+/// Sends the contents of `read` in chunks, until it ends or the receiver is
+/// dropped.
+fn send_chunks(read: &mut dyn Read, sender: &Chunks) -> io::Result<()> {
+    loop {
+        let mut chunk = vec![0; CHUNK_SIZE];
+        let len = read.read(&mut chunk)?;
+        if len == 0 {
+            return Ok(());
+        }
+        chunk.truncate(len);
+        if sender.send(Ok(chunk)).is_err() {
+            return Ok(());
+        }
+    }
+}
+
+/// Reads the chunks of a member as they are decompressed.
+struct ChunkReader {
+    receiver: Receiver<io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.chunk.len() {
+            match self.receiver.recv() {
+                Ok(chunk) => {
+                    self.chunk = chunk?;
+                    self.pos = 0;
+                }
+                // The sender is dropped after the last chunk.
+                Err(_) => return Ok(0),
+            }
+        }
+        let len = buf.len().min(self.chunk.len() - self.pos);
+        buf[..len].copy_from_slice(&self.chunk[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+fn archive_name(path: &Path) -> String {
+    path.parent()
+        .map(|archive| archive.display().to_string())
+        .unwrap_or_default()
+}
+
+/// Parses an `--encoding`. Only encodings in which the markup is ASCII can be
+/// read, which excludes e.g. UTF-16.
+pub fn parse_encoding(s: &str) -> Result<&'static Encoding> {
+    match Encoding::for_label(s.as_bytes()) {
+        Some(encoding) if encoding.is_ascii_compatible() => Ok(encoding),
+        Some(encoding) => bail!(
+            "{} is not supported, since it is not ASCII-compatible",
+            encoding.name()
+        ),
+        None => bail!("unknown encoding, expected a label such as `utf-8` or `windows-1252`"),
+    }
+}
+
+/// Unescapes an attribute value and decodes it from `encoding`. Values without
+/// entities in UTF-8 or in ASCII, i.e. most of them, are borrowed from the
+/// element instead of being copied.
+fn parse_attribute<'a>(
+    attribute: Attribute<'a>,
+    encoding: &'static Encoding,
+) -> Result<Cow<'a, str>> {
+    let value = match attribute.value {
+        Cow::Borrowed(value) => unescape(value),
+        Cow::Owned(value) => unescape(&value).map(|value| Cow::Owned(value.into_owned())),
+    };
+    let value = value
+        .map_err(quick_xml::Error::EscapeError)
+        .context("error escaping attribute value")?;
+
+    if encoding != UTF_8 {
+        let decoded = match value {
+            Cow::Borrowed(value) => {
+                encoding.decode_without_bom_handling_and_without_replacement(value)
+            }
+            Cow::Owned(value) => encoding
+                .decode_without_bom_handling_and_without_replacement(&value)
+                .map(|value| Cow::Owned(value.into_owned())),
+        };
+        return decoded.with_context(|| format!("invalid {} in attribute value", encoding.name()));
+    }
+
+    match value {
+        Cow::Borrowed(value) => str::from_utf8(value).map(Cow::Borrowed),
+        Cow::Owned(value) => String::from_utf8(value)
+            .map(Cow::Owned)
+            .map_err(|e| e.utf8_error()),
+    }
+    .context("invalid utf-8 in attribute value, see --encoding if the file is not UTF-8")
+}
@@ -0,0 +1,67 @@
+use crate::formatter::Formatter;
+
+use anyhow::Result;
+use rio_api::model::{NamedNode, NamedOrBlankNode, Term, Triple};
+
+/// Every relation with the predicate of its reverse, which points from the
+/// object back to the subject. `tag.cooccurs_with` is its own reverse.
+pub const INVERSES: &[(&str, &str)] = &[
+    ("badge.user", "user.badge"),
+    ("comment.post", "post.comment"),
+    ("comment.user", "user.comment"),
+    ("post.accepted_answer", "post.accepted_answer_of"),
+    ("post.parent", "post.answer"),
+    ("post.owner", "user.post"),
+    ("post.last_editor", "user.last_edited_post"),
+    ("post.tags", "tag.post"),
+    ("posthistory.post", "post.history"),
+    ("posthistory.user", "user.post_history"),
+    ("postlink.post", "post.link"),
+    ("postlink.related_post", "post.related_link"),
+    ("tag.excerpt_post", "post.excerpt_of"),
+    ("tag.wiki_post", "post.wiki_of"),
+    ("tag.cooccurs_with", "tag.cooccurs_with"),
+    ("cooccurrence.tag", "tag.cooccurrence"),
+    ("user.edited", "post.edited_by"),
+    ("vote.post", "post.vote"),
+    ("vote.user", "user.vote"),
+];
+
+/// Writes every triple, and for the relations in `INVERSES` also the reverse
+/// triple, for `--emit-inverse`: `_:c456 comment.post _:p123` is followed by
+/// `_:p123 post.comment _:c456`. Nothing is kept between triples.
+pub struct Inverses<'a> {
+    inner: &'a mut dyn Formatter,
+}
+
+impl<'a> Inverses<'a> {
+    pub fn new(inner: &'a mut dyn Formatter) -> Self {
+        Inverses { inner }
+    }
+}
+
+impl Formatter for Inverses<'_> {
+    fn format(&mut self, triple: &Triple) -> Result<()> {
+        self.inner.format(triple)?;
+
+        let inverse = INVERSES
+            .iter()
+            .find(|&&(predicate, _)| predicate == triple.predicate.iri)
+            .map(|&(_, inverse)| inverse);
+        match (inverse, triple.subject, triple.object) {
+            (Some(iri), NamedOrBlankNode::BlankNode(subject), Term::BlankNode(object)) => {
+                self.inner.format(&Triple {
+                    subject: object.into(),
+                    predicate: NamedNode { iri },
+                    object: subject.into(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The inner formatter is borrowed, so it is finished by its owner.
+    fn finish(self: Box<Self>) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use crate::formatter::Formatter;
+
+use anyhow::{bail, Result};
+use rio_api::model::{NamedNode, NamedOrBlankNode, Term, Triple};
+
+/// Parses a `--base-iri`, which must be absolute. A `/` is appended unless
+/// it already ends with `/` or `#`.
+pub fn parse_base_iri(s: &str) -> Result<String> {
+    if !s.contains(':') {
+        bail!("expected an absolute IRI such as `https://example.org/so/`");
+    }
+    let mut base = s.to_string();
+    if !base.ends_with('/') && !base.ends_with('#') {
+        base.push('/');
+    }
+    Ok(base)
+}
+
+/// Writes the nodes of every triple as IRIs under `--base-iri` instead of as
+/// blank nodes, so that separately generated outputs can refer to each other.
+/// The prefix of an id is replaced by a path segment named after its kind,
+/// e.g. `p123` becomes `<base>post/123`. Ids without a known prefix, such as
+/// `stats`, are appended as they are.
+pub struct NodeIris {
+    inner: Box<dyn Formatter + Send>,
+    base: String,
+    /// The prefix of every kind of node, with the kind as its segment.
+    segments: Vec<(String, &'static str)>,
+    subject: String,
+    object: String,
+}
+
+impl NodeIris {
+    pub fn new(
+        inner: Box<dyn Formatter + Send>,
+        base: String,
+        prefixes: &[(&'static str, &str)],
+    ) -> Self {
+        let segments = prefixes
+            .iter()
+            .map(|&(kind, prefix)| (prefix.to_string(), kind))
+            .collect();
+        NodeIris {
+            inner,
+            base,
+            segments,
+            subject: String::new(),
+            object: String::new(),
+        }
+    }
+}
+
+impl Formatter for NodeIris {
+    fn format(&mut self, triple: &Triple) -> Result<()> {
+        let subject = match triple.subject {
+            NamedOrBlankNode::BlankNode(node) => {
+                node_iri(&self.base, &self.segments, node.id, &mut self.subject);
+                NamedNode { iri: &self.subject }.into()
+            }
+            subject => subject,
+        };
+        let object = match triple.object {
+            Term::BlankNode(node) => {
+                node_iri(&self.base, &self.segments, node.id, &mut self.object);
+                NamedNode { iri: &self.object }.into()
+            }
+            object => object,
+        };
+        self.inner.format(&Triple {
+            subject,
+            predicate: triple.predicate,
+            object,
+        })
+    }
+
+    fn set_graph(&mut self, entity: &str) {
+        self.inner.set_graph(entity)
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.inner.finish()
+    }
+}
+
+fn node_iri(base: &str, segments: &[(String, &str)], id: &str, buf: &mut String) {
+    buf.clear();
+    buf.push_str(base);
+    // No prefix starts with another one, so at most one of them matches.
+    let segment = segments.iter().find_map(|(prefix, segment)| {
+        let rest = id.strip_prefix(prefix.as_str())?;
+        Some((*segment, rest))
+    });
+    match segment {
+        Some((segment, rest)) if !rest.is_empty() => {
+            buf.push_str(segment);
+            buf.push('/');
+            buf.push_str(rest);
+        }
+        _ => buf.push_str(id),
+    }
+}
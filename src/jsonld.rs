@@ -0,0 +1,153 @@
+use crate::datatype::{XSD_BOOLEAN, XSD_DATETIME, XSD_INTEGER};
+use crate::formatter::{absolute_iri, Formatter};
+use crate::output::Output;
+
+use anyhow::Result;
+use rio_api::model::{Literal, NamedOrBlankNode, Term, Triple};
+use serde_json::{json, Map, Value};
+
+/// Entities whose predicates get a prefix in the `@context`, e.g. `post`
+/// for `post.score`.
+const ENTITIES: &[&str] = &[
+    "badge",
+    "comment",
+    "post",
+    "posthistory",
+    "postlink",
+    "tag",
+    "user",
+    "vote",
+    "cooccurrence",
+    "statement",
+    "stats",
+];
+
+/// Writes a JSON-LD document with a node object per entity in its `@graph`.
+///
+/// The `write_*` functions emit the triples of an entity consecutively, so
+/// triples are grouped by looking at the previous subject only, and nodes are
+/// written out as soon as the next one starts.
+///
+/// The document is only closed by `finish`, so two outputs cannot be
+/// concatenated into one, see `OutputFormat::concatenates`.
+pub struct JsonLdFormatter<W: Output> {
+    write: W,
+    namespace: String,
+    id: String,
+    node: Map<String, Value>,
+    first: bool,
+    buf: String,
+}
+
+impl<W: Output> JsonLdFormatter<W> {
+    /// Creates a formatter with the predicates under `namespace`.
+    pub fn new(mut write: W, namespace: &str) -> Result<Self> {
+        let mut context = Map::new();
+        for entity in ENTITIES {
+            let prefix = format!("{}{}.", namespace, entity);
+            context.insert(entity.to_string(), Value::from(prefix));
+        }
+        context.insert(
+            "xsd".to_string(),
+            Value::from("http://www.w3.org/2001/XMLSchema#"),
+        );
+
+        write.write_all(b"{\"@context\":")?;
+        serde_json::to_writer(&mut write, &context)?;
+        write.write_all(b",\"@graph\":[\n")?;
+        Ok(JsonLdFormatter {
+            write,
+            namespace: namespace.to_string(),
+            id: String::new(),
+            node: Map::new(),
+            first: true,
+            buf: String::new(),
+        })
+    }
+
+    fn flush_node(&mut self) -> Result<()> {
+        if self.node.is_empty() {
+            return Ok(());
+        }
+
+        if !self.first {
+            self.write.write_all(b",\n")?;
+        }
+        self.first = false;
+        self.node
+            .insert("@id".to_string(), Value::from(self.id.as_str()));
+        serde_json::to_writer(&mut self.write, &self.node)?;
+        self.node.clear();
+        Ok(())
+    }
+}
+
+impl<W: Output> Formatter for JsonLdFormatter<W> {
+    fn format(&mut self, triple: &Triple) -> Result<()> {
+        let subject = node_id(&self.namespace, triple.subject, &mut self.buf);
+        if subject != self.id {
+            self.flush_node()?;
+            self.id = subject;
+        }
+
+        let value = match triple.object {
+            Term::NamedNode(node) => {
+                json!({ "@id": absolute_iri(&self.namespace, node.iri, &mut self.buf) })
+            }
+            Term::BlankNode(node) => json!({ "@id": format!("_:{}", node.id) }),
+            Term::Literal(Literal::Simple { value }) => Value::from(value),
+            Term::Literal(Literal::LanguageTaggedString { value, language }) => {
+                json!({ "@value": value, "@language": language })
+            }
+            Term::Literal(Literal::Typed { value, datatype }) => {
+                json!({ "@value": value, "@type": compact_datatype(datatype.iri) })
+            }
+        };
+
+        // Predicates such as `post.tags` have several values, which are
+        // collected into an array.
+        let key = property(&self.namespace, triple.predicate.iri, &mut self.buf);
+        match self.node.get_mut(&key) {
+            Some(Value::Array(values)) => values.push(value),
+            Some(previous) => *previous = Value::Array(vec![previous.take(), value]),
+            None => {
+                self.node.insert(key, value);
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.flush_node()?;
+        self.write.write_all(b"\n]}\n")?;
+        self.write.finish()?;
+        Ok(())
+    }
+}
+
+fn node_id(namespace: &str, node: NamedOrBlankNode, buf: &mut String) -> String {
+    match node {
+        NamedOrBlankNode::NamedNode(node) => absolute_iri(namespace, node.iri, buf).to_string(),
+        NamedOrBlankNode::BlankNode(node) => format!("_:{}", node.id),
+    }
+}
+
+/// The key of a predicate: a compact IRI such as `post:score` for the
+/// entities in the `@context`, or the full IRI otherwise.
+fn property(namespace: &str, iri: &str, buf: &mut String) -> String {
+    match iri.split_once('.') {
+        Some((entity, local)) if !iri.contains(':') && ENTITIES.contains(&entity) => {
+            format!("{}:{}", entity, local)
+        }
+        _ => absolute_iri(namespace, iri, buf).to_string(),
+    }
+}
+
+fn compact_datatype(iri: &str) -> &str {
+    match iri {
+        XSD_BOOLEAN => "xsd:boolean",
+        XSD_DATETIME => "xsd:dateTime",
+        XSD_INTEGER => "xsd:integer",
+        _ => iri,
+    }
+}
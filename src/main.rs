@@ -1,130 +1,2083 @@
-use anyhow::{Context, Result};
-use clap::Clap;
-use data_encoding::BASE32_NOPAD;
-use flate2::write::GzEncoder;
-use flate2::Compression;
-use log::info;
+mod arrow;
+mod cooccurrence;
+mod datatype;
+mod dedup;
+mod ego;
+mod formatter;
+mod hash;
+mod id;
+mod input;
+mod inverse;
+mod iri;
+mod jsonld;
+mod ontology;
+mod output;
+mod partition;
+mod plaintext;
+mod progress;
+mod protobuf;
+mod rank;
+mod rdfxml;
+mod records;
+mod reify;
+mod resume;
+mod sanitize;
+mod stats;
+mod stop;
+#[cfg(feature = "store")]
+mod store;
+mod style;
+mod types;
+mod validate;
+mod void;
+mod warnings;
+
+use crate::cooccurrence::TagCooccurrence;
+use crate::datatype::{
+    is_datetime, is_integer, is_uri, year_of, XSD_ANY_URI, XSD_BOOLEAN, XSD_DATETIME, XSD_GYEAR,
+    XSD_INTEGER,
+};
+use crate::dedup::{Dedup, Deduplicate};
+use crate::ego::collect_ego;
+use crate::formatter::{new_formatter, Counter, Formatter, OutputFormat, Tee, PREDICATE_NAMESPACE};
+use crate::hash::{anonymous_user_id, content_hash, user_pseudonym, HashAlgorithm};
+use crate::id::{check_prefixes, parse_prefix, prefixed, PREFIXES};
+use crate::input::{
+    for_each_row, for_each_tracked_row, input_exists, input_size, is_stdin, parse_encoding,
+    InputFormat, OnError, ReadOptions, Row,
+};
+use crate::inverse::Inverses;
+use crate::iri::{parse_base_iri, NodeIris};
+use crate::ontology::{print_schema, write_ontology};
+use crate::output::{concatenate, is_stdout, open_output, Compression, Encoder};
+use crate::partition::TagPartitions;
+use crate::plaintext::{html_to_text, normalize_line_endings};
+use crate::progress::{set_records, Progress};
+use crate::rank::{collect_answer_ranks, AnswerRanks};
+use crate::records::{convert_in_parallel, Converted};
+use crate::reify::Reify;
+use crate::resume::{checkpoint_path, Checkpoint};
+use crate::sanitize::StripControlChars;
+use crate::stats::StatsFormatter;
+use crate::stop::{parse_duration, stop_after, TIMEOUT_EXIT_CODE};
+#[cfg(feature = "store")]
+use crate::store::StoreFormatter;
+use crate::style::{PredicateStyle, StyledFormatter};
+use crate::types::{type_name, LINK_TYPES, POST_HISTORY_TYPES, POST_TYPES, RDF_TYPE};
+use crate::validate::Validator;
+use crate::void::{write_predicates, write_void, OutputCounter, OutputStats};
+use crate::warnings::{Category, Warnings};
+
+use anyhow::{bail, Context, Result};
+use clap::{AppSettings, ArgEnum, ArgSettings, Clap, FromArgMatches, IntoApp};
+use data_encoding::{BASE32_NOPAD, HEXUPPER};
+use encoding_rs::{Encoding, UTF_8};
+use env_logger::Target;
+use indicatif::ProgressBar;
 use log::LevelFilter;
-use quick_xml::events::attributes::Attribute;
-use quick_xml::events::{BytesStart, Event};
-use quick_xml::Reader;
-use rio_api::formatter::TriplesFormatter;
+use log::{info, warn};
+use regex::Regex;
 use rio_api::model::{BlankNode, Literal, NamedNode, Triple};
-use rio_turtle::NTriplesFormatter;
-
-use std::fs::File;
-use std::io::{BufWriter, Write};
+use serde_json::{Map, Value};
+
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
 use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
 
 fn main() -> Result<()> {
-    env_logger::Builder::new()
-        .filter_level(LevelFilter::max())
-        .init();
-    let opts = Opts::parse();
-
-    let output_file = File::create(opts.path_to).context("Could not create output file")?;
-    let output_writer = BufWriter::new(output_file);
-    let output_writer = GzEncoder::new(output_writer, Compression::best());
-    let mut formatter = NTriplesFormatter::new(output_writer);
-
-    write_rdf(
-        "Badges",
-        &mut formatter,
-        write_badge,
-        opts.path_from.join("Badges.xml"),
-    )?;
-
-    write_rdf(
-        "Comments",
-        &mut formatter,
-        write_comment,
-        opts.path_from.join("Comments.xml"),
-    )?;
-
-    write_rdf(
-        "Posts",
-        &mut formatter,
-        write_post,
-        opts.path_from.join("Posts.xml"),
-    )?;
-
-    write_rdf(
-        "PostHistory",
-        &mut formatter,
-        write_posthistory,
-        opts.path_from.join("PostHistory.xml"),
-    )?;
-
-    write_rdf(
-        "PostLinks",
-        &mut formatter,
-        write_postlink,
-        opts.path_from.join("PostLinks.xml"),
-    )?;
-
-    write_rdf(
-        "Tags",
-        &mut formatter,
-        write_tag,
-        opts.path_from.join("Tags.xml"),
-    )?;
-
-    write_rdf(
-        "Users",
-        &mut formatter,
-        write_user,
-        opts.path_from.join("Users.xml"),
-    )?;
-
-    formatter.finish();
+    // The input and output are only required without a subcommand. After
+    // `--` or any option, `schema` is the input again rather than a subcommand.
+    let matches = Opts::into_app()
+        .subcommand(SchemaOpts::into_app())
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .setting(AppSettings::ArgsNegateSubcommands)
+        .setting(AppSettings::DisableHelpSubcommand)
+        .get_matches();
+    if let Some(("schema", matches)) = matches.subcommand() {
+        return print_schema(SchemaOpts::from_arg_matches(matches).json);
+    }
+    let mut opts = Opts::from_arg_matches(&matches);
+    let progress = if opts.no_progress {
+        None
+    } else {
+        Progress::new()
+    };
+    let mut logger = env_logger::Builder::new();
+    // `RUST_LOG` replaces the levels of `-v` and `-q` as a whole.
+    if env::var_os("RUST_LOG").is_some() {
+        logger.parse_default_env();
+    } else {
+        logger.filter_level(LevelFilter::Warn);
+        logger.filter_module(env!("CARGO_CRATE_NAME"), opts.log_level());
+    }
+    if let Some(progress) = &progress {
+        logger.target(Target::Pipe(Box::new(progress.log_writer())));
+    }
+    logger.init();
+    if is_stdin(&opts.path_from) {
+        check_stdin(&opts)?;
+    } else if opts.stdin_entity.is_some() {
+        bail!("--stdin-entity requires `-` as the input");
+    }
+    if is_stdin(&opts.path_from) && !opts.input_file.is_empty() {
+        bail!("--input-file requires a directory or archive as the input, not stdin");
+    }
+    if opts.input_format == InputFormat::Csv && opts.encoding.is_some_and(|e| e != UTF_8) {
+        bail!("--encoding only applies to XML input, CSV files are always read as UTF-8");
+    }
+    if opts.passthrough_unknown && opts.anonymize {
+        bail!("--passthrough-unknown writes personal data as is, so it conflicts with --anonymize");
+    }
+    if let Some(emit_schema) = &opts.emit_schema {
+        write_ontology(emit_schema, &opts)?;
+    }
+    check_prefixes(&opts.prefixes())?;
+    opts.view_bucket_thresholds.sort_unstable();
+    opts.view_bucket_thresholds.dedup();
+    if opts.parallel == Some(Parallel::Records)
+        && (opts.posts_by_tag.is_some() || opts.tag_cooccurrence)
+    {
+        bail!(
+            "--posts-by-tag and --tag-cooccurrence need the posts in one place, so they cannot \
+             be combined with --parallel records"
+        );
+    }
+    if opts.resume {
+        if opts.dry_run {
+            bail!("--dry-run writes no output, so there is nothing to --resume");
+        }
+        if is_stdout(&opts.path_to) {
+            bail!("--resume needs an output file or directory, not stdout");
+        }
+        if opts.parallel == Some(Parallel::Shared) {
+            bail!(
+                "--resume writes a file per entity, so it cannot be combined with --parallel \
+                 shared"
+            );
+        }
+        if opts.merge {
+            bail!("--resume rewrites unfinished entities, so it cannot be combined with --merge");
+        }
+    }
+    if opts.store.is_some() {
+        if !cfg!(feature = "store") {
+            bail!("--store requires stackoverflow2rdf to be built with `--features store`");
+        }
+        if !is_stdout(&opts.path_to) {
+            bail!("--store loads the triples instead of writing them, so the output must be `-`");
+        }
+        if opts.split || opts.resume || opts.parallel == Some(Parallel::Split) {
+            bail!(
+                "--store loads the triples into one store, so it cannot be combined with \
+                 --split, --parallel split or --resume"
+            );
+        }
+    }
+    let parallel_entities = matches!(opts.parallel, Some(Parallel::Shared | Parallel::Split));
+    if opts.limit_total.is_some() && (parallel_entities || opts.split || opts.resume) {
+        bail!(
+            "--limit-total needs the entities to be written one after the other into one \
+             output, so it cannot be combined with --parallel shared or split, --split or \
+             --resume"
+        );
+    }
+    if opts.dry_run {
+        info!("Dry run: the triples are converted but not written");
+    } else if opts.split {
+        if is_stdout(&opts.path_to) {
+            bail!("--split needs an output directory, not stdout");
+        }
+        if opts.parallel == Some(Parallel::Shared) {
+            bail!(
+                "--split writes a file per entity, so it cannot be combined with --parallel shared"
+            );
+        }
+        fs::create_dir_all(&opts.path_to).with_context(|| {
+            format!(
+                "Could not create output directory {}",
+                opts.path_to.display()
+            )
+        })?;
+    }
+    let extension = opts.path_to.extension().and_then(|ext| ext.to_str());
+    match opts.compression.extension() {
+        _ if is_stdout(&opts.path_to) || opts.split || opts.dry_run => {}
+        Some(expected) if extension != Some(expected) => warn!(
+            "output file {} does not end with .{}",
+            opts.path_to.display(),
+            expected
+        ),
+        None => {
+            if let Some(extension @ ("gz" | "zst")) = extension {
+                warn!(
+                    "output file {} is not compressed, but ends with .{}",
+                    opts.path_to.display(),
+                    extension
+                );
+            }
+        }
+        _ => {}
+    }
+    if let Some(level) = opts.compression_level {
+        if !opts.compression.levels().contains(&level) {
+            warn!(
+                "--compression-level {} is out of range, using {}",
+                level,
+                opts.compression_level()
+            );
+        }
+    }
+    if opts.exclude_users_posts && opts.exclude_users_matching.is_none() {
+        bail!("--exclude-users-posts requires --exclude-users-matching");
+    }
+    check_concatenation(&opts)?;
+    if let Some(timeout) = opts.timeout {
+        stop_after(timeout);
+    }
+
+    let accepted_answers = if opts.posts_only_with_accepted {
+        Some(collect_accepted_answers(&opts)?)
+    } else {
+        None
+    };
+    let post_ids = if opts.drop_dangling_comments {
+        Some(collect_post_ids(&opts, accepted_answers.as_ref())?)
+    } else {
+        None
+    };
+    let post_owners = if opts.editor_edges {
+        Some(collect_post_owners(&opts)?)
+    } else {
+        None
+    };
+    let answer_ranks = if opts.answer_rank {
+        Some(collect_answer_ranks(&opts)?)
+    } else {
+        None
+    };
+    let ego = match opts.ego {
+        Some(seed) => Some(collect_ego(&opts, seed, opts.depth)?),
+        None => None,
+    };
+    let excluded_users = match &opts.exclude_users_matching {
+        Some(pattern) if opts.exclude_users_posts => Some(collect_excluded_users(&opts, pattern)?),
+        _ => None,
+    };
+    let posts_by_tag = match &opts.posts_by_tag {
+        Some(dir) if !opts.dry_run => Some(TagPartitions::new(
+            dir.clone(),
+            opts.format,
+            opts.compression,
+            opts.compression_level(),
+            opts.posts_by_tag_max_open,
+            opts.merge,
+            Box::new(|write| output_formatter(&opts, write)),
+        )?),
+        _ => None,
+    };
+    let tag_cooccurrence = if opts.tag_cooccurrence {
+        Some(TagCooccurrence::new(
+            opts.cooccurrence_min_weight,
+            opts.cooccurrence_max_pairs,
+        ))
+    } else {
+        None
+    };
+    let checkpoint = if opts.resume {
+        Some(Checkpoint::open(checkpoint_path(&opts.path_to))?)
+    } else {
+        None
+    };
+    let state = State {
+        opts: &opts,
+        accepted_answers: accepted_answers.as_ref(),
+        post_ids: post_ids.as_ref(),
+        post_owners: post_owners.as_ref(),
+        answer_ranks: answer_ranks.as_ref(),
+        ego: ego.as_ref(),
+        excluded_users: excluded_users.as_ref(),
+        progress: progress.as_ref(),
+        checkpoint: checkpoint.as_ref(),
+        posts_by_tag,
+        tag_cooccurrence,
+        unknown_attributes: HashMap::new(),
+        remaining_records: opts.limit_total,
+        output_stats: if opts.emit_void.is_some() || opts.emit_predicates.is_some() {
+            Some(OutputStats::default())
+        } else {
+            None
+        },
+        warnings: Warnings::default(),
+        counts: Vec::new(),
+    };
+
+    let report = match opts.parallel {
+        // Only the output of a sequential or shared run can be discarded.
+        None | Some(Parallel::Records) if opts.dry_run => write_sequential(state)?,
+        Some(_) if opts.dry_run => write_shared(state)?,
+        None | Some(Parallel::Records) if opts.split || opts.resume => write_split(state)?,
+        None | Some(Parallel::Records) => write_sequential(state)?,
+        Some(Parallel::Shared) => write_shared(state)?,
+        Some(Parallel::Split) => write_split(state)?,
+    };
+
+    if let Some(checkpoint) = checkpoint {
+        // A stopped run is resumed like an interrupted one.
+        if !stop::requested() {
+            checkpoint.remove()?;
+        }
+    }
+
+    report.warnings.log_summary();
+    report.log_skipped();
+    if let Some(warnings_json) = &opts.warnings_json {
+        report.warnings.write_json(warnings_json)?;
+    }
+    if let Some(stats_json) = &opts.stats_json {
+        report.write_json(stats_json)?;
+    }
+    if let Some(emit_void) = &opts.emit_void {
+        write_void(emit_void, &opts, &report)?;
+    }
+    if let Some(emit_predicates) = &opts.emit_predicates {
+        write_predicates(emit_predicates, &opts, &report)?;
+    }
+
+    if stop::requested() {
+        process::exit(TIMEOUT_EXIT_CODE);
+    }
+
+    let invalid = report.warnings.count(Category::InvalidTriple);
+    if opts.fail_on_invalid && invalid != 0 {
+        bail!("{} invalid triples were written", invalid);
+    }
+
+    if opts.fail_on_empty_entity {
+        for &(name, _) in WRITERS.iter().filter(|(name, _)| opts.selects(name)) {
+            if report.triples(name) == 0 {
+                bail!("{}: no triples were written", name);
+            }
+        }
+    }
+    if opts.fail_on_empty && WRITERS.iter().all(|&(name, _)| report.triples(name) == 0) {
+        bail!("no triples were written");
+    }
 
     Ok(())
 }
 
+/// Prints every predicate that is written, with the entity of its subjects
+/// and what its objects are, without reading a dump.
+#[derive(Clap)]
+#[clap(name = "schema")]
+struct SchemaOpts {
+    /// Print a JSON array instead of a table, for tooling.
+    #[clap(long)]
+    json: bool,
+}
+
 #[derive(Clap)]
 struct Opts {
+    /// Directory or archive of the dump, or `-` to read a single entity
+    /// from stdin, see `--stdin-entity`.
     #[clap(name = "xml_directory")]
     path_from: PathBuf,
+    /// Output file, or `-` to write to stdout. A directory with `--split`.
     #[clap(name = "output.rdf.gz")]
     path_to: PathBuf,
+    /// Write each entity to its own file in the output directory, e.g.
+    /// `posts.nt.gz`, so that entities can be reloaded separately.
+    #[clap(long)]
+    split: bool,
+    /// Parse and convert everything as usual, but discard the triples instead
+    /// of writing them, e.g. to check that a dump parses before a long run.
+    /// No output files are created.
+    #[clap(long)]
+    dry_run: bool,
+    /// Keep track of the entities that were written completely in a
+    /// checkpoint next to the output, e.g. `output.nt.gz.resume`, and skip
+    /// them when the same command is run again after an interruption. The
+    /// entities are written to files of their own, as with `--parallel split`
+    /// or `--split`, so that finished ones can be kept. RDF/XML, JSON-LD and
+    /// Arrow outputs cannot be concatenated, so they need `--split`.
+    #[clap(long)]
+    resume: bool,
+    /// Format of the input files. With `csv`, files are expected to be named
+    /// `Posts.csv`, `Users.csv`, etc.
+    #[clap(long, arg_enum, default_value = "xml")]
+    input_format: InputFormat,
+    /// Memory-map the input files instead of reading them, which saves a copy
+    /// of every byte on fast disks. Files that cannot be mapped, e.g. on some
+    /// network filesystems, are read as usual.
+    #[clap(long)]
+    mmap: bool,
+    /// Size of the buffers that the input is read into and that the output is
+    /// written from, in KiB. Larger buffers help with huge `Body` or
+    /// `AboutMe` fields, which otherwise grow them a few times at the start.
+    #[clap(long, value_name = "kb", default_value = "64")]
+    read_buffer: usize,
+    /// Format of the output file, see `--compression` for its compression.
+    #[clap(long, arg_enum, default_value = "ntriples")]
+    format: OutputFormat,
+    /// Compression of the output files.
+    #[clap(long, arg_enum, default_value = "gzip")]
+    compression: Compression,
+    /// Compression level, 0-9 for gzip (0 stores the data uncompressed) and
+    /// 1-22 for zstd. Defaults to 9 for gzip and 19 for zstd.
+    #[clap(long)]
+    compression_level: Option<u32>,
+    /// Compress gzip output on this many threads, which pays off at high
+    /// levels. The output is still a single gzip stream.
+    #[clap(long, value_name = "n", default_value = "1")]
+    compress_threads: usize,
+    /// Append to the output file instead of overwriting it. The new triples
+    /// are written as an additional gzip member or zstd frame, which
+    /// decompress as a single stream. Nothing is deduplicated: merging
+    /// overlapping inputs produces duplicate triples. RDF/XML, JSON-LD and
+    /// Arrow outputs are single documents, so they cannot be merged.
+    #[clap(long)]
+    merge: bool,
+    /// Insert the triples into an Oxigraph store in this directory instead of
+    /// writing them, with `-` as the output. The store is created if needed,
+    /// and added to otherwise. Requires building with `--features store`.
+    #[clap(long, value_name = "dir")]
+    store: Option<PathBuf>,
+    /// Write the warning counts per category to this file as JSON.
+    #[clap(long)]
+    warnings_json: Option<PathBuf>,
+    /// Write the number of records read and triples written per entity to
+    /// this file as JSON, along with the total number of triples.
+    #[clap(long)]
+    stats_json: Option<PathBuf>,
+    /// Write a VoID description of the output to this file in Turtle, with
+    /// the number of triples, distinct predicates and records per class, for
+    /// data catalogs.
+    #[clap(long, value_name = "path")]
+    emit_void: Option<PathBuf>,
+    /// Write every predicate that occurs in the output to this file, one per
+    /// line in alphabetical order, as it is written with `--predicate-style`
+    /// and `--predicate-base`.
+    #[clap(long, value_name = "path")]
+    emit_predicates: Option<PathBuf>,
+    /// Write an RDFS/OWL ontology of the classes and predicates of the output
+    /// to this file as Turtle, for tools such as Protégé.
+    #[clap(long, value_name = "path")]
+    emit_schema: Option<PathBuf>,
+    /// Skip triples that were already written in this run, e.g. when sharded
+    /// files overlap. `--dedup` alone is `--dedup=exact`, which keeps a hash
+    /// of every distinct triple in memory; `--dedup=approx` uses a fixed
+    /// amount of memory but may drop some distinct triples.
+    #[clap(
+        long,
+        arg_enum,
+        value_name = "mode",
+        min_values = 0,
+        require_equals = true,
+        default_missing_value = "exact"
+    )]
+    dedup: Option<Dedup>,
+    /// Size of the Bloom filter of `--dedup=approx` in MiB. About 10 bits per
+    /// distinct triple keep the rate of dropped triples around 1%, so the
+    /// default fits about 850 million triples.
+    #[clap(long, value_name = "MiB", default_value = "1024")]
+    dedup_memory: u64,
+    /// Keep the control characters that XML does not allow in literals, such
+    /// as `\u{7}`, instead of removing them.
+    #[clap(long)]
+    keep_control_chars: bool,
+    /// Check every triple for invalid IRIs, blank node ids and, with
+    /// `--keep-control-chars`, characters before it is written, and log the
+    /// records that produce them as `invalid_triple` warnings. Slows down the
+    /// run.
+    #[clap(long)]
+    validate: bool,
+    /// Fail at the end of the run if `--validate` found invalid triples.
+    #[clap(long, requires = "validate")]
+    fail_on_invalid: bool,
+    /// Only emit questions that have an accepted answer, along with those
+    /// accepted answers. Unanswered questions, questions without an accepted
+    /// answer, non-accepted answers and all other post types are dropped.
+    /// Requires an extra pass over Posts.xml.
+    #[clap(long)]
+    posts_only_with_accepted: bool,
+    /// Treat this value of a numeric or relation predicate as absent, e.g.
+    /// `--sentinel post.owner=-1`. No triple is emitted for it. May be given
+    /// multiple times.
+    #[clap(
+        long,
+        value_name = "predicate=value",
+        number_of_values = 1,
+        parse(try_from_str = parse_predicate_value)
+    )]
+    sentinel: Vec<(String, String)>,
+    /// Use this value when the attribute of a required predicate is missing,
+    /// e.g. `--default post.content_license="CC BY-SA 4.0"`, instead of
+    /// aborting. May be given multiple times.
+    #[clap(
+        long,
+        value_name = "predicate=value",
+        number_of_values = 1,
+        parse(try_from_str = parse_predicate_value)
+    )]
+    default: Vec<(String, String)>,
+    /// Emit a `post.content_hash` of each post's title and body, computed with
+    /// the given algorithm. See the README for how the text is normalized.
+    #[clap(long, arg_enum, value_name = "algorithm")]
+    content_hash: Option<HashAlgorithm>,
+    /// Only emit the neighbourhood of this post: the post itself and every
+    /// entity within `--depth` hops of it (answers, comments, linked posts,
+    /// authors, tags, ...). Each hop is an extra pass over all input files.
+    #[clap(long, value_name = "post-id")]
+    ego: Option<u64>,
+    /// Number of hops from the `--ego` post to include.
+    #[clap(long, default_value = "1")]
+    depth: u32,
+    /// Also write each question to `<dir>/<tag>.nt.gz` for every tag it has,
+    /// so that a question with several tags appears in several files. Answers
+    /// carry no tags in the dump and are not partitioned.
+    #[clap(long, value_name = "dir")]
+    posts_by_tag: Option<PathBuf>,
+    /// Maximum number of per-tag files kept open at once by `--posts-by-tag`.
+    #[clap(long, default_value = "256")]
+    posts_by_tag_max_open: usize,
+    /// Emit `tag.cooccurs_with` edges between tags that appear on the same
+    /// post, along with a weighted `cooccurrence` node per pair.
+    #[clap(long)]
+    tag_cooccurrence: bool,
+    /// Only emit tag pairs that co-occur on at least this many posts.
+    #[clap(long, default_value = "1")]
+    cooccurrence_min_weight: u64,
+    /// Maximum number of tag pairs kept in memory before rare pairs are pruned.
+    /// Weights become approximate once pruning happens.
+    #[clap(long, default_value = "10000000")]
+    cooccurrence_max_pairs: usize,
+    /// Stop once the run has taken this long (e.g. `90s`, `15m`, `2h`). The
+    /// current record is finished and the output is closed cleanly, so it is
+    /// valid but partial. Exits with code 124.
+    #[clap(long, value_name = "duration", parse(try_from_str = parse_duration))]
+    timeout: Option<Duration>,
+    /// Also emit an `rdf:Statement` for every triple with this predicate,
+    /// along with the file and record it came from. May be given multiple
+    /// times; `all` reifies every predicate, which multiplies the output size
+    /// several times over.
+    #[clap(long, value_name = "predicate", number_of_values = 1)]
+    reify: Vec<String>,
+    /// Emit a `user.edited` edge from every user who edited the title, body or
+    /// tags of someone else's post to that post. Requires an extra pass over
+    /// Posts.xml.
+    #[clap(long)]
+    editor_edges: bool,
+    /// Only emit users with at least this reputation. Posts, comments, etc.
+    /// by the dropped users keep their edges to them, which then dangle.
+    /// Users whose reputation is not a number are dropped too, with a warning.
+    #[clap(long, value_name = "n")]
+    min_reputation: Option<i64>,
+    /// Drop users whose `DisplayName` matches this regular expression, e.g.
+    /// `^Community$|[Bb]ot$` for automated accounts. Posts, comments, etc. by
+    /// the dropped users keep their edges to them, which then dangle, unless
+    /// `--exclude-users-posts` is set.
+    #[clap(long, value_name = "regex", parse(try_from_str = Regex::new))]
+    exclude_users_matching: Option<Regex>,
+    /// Also drop the posts and comments of the users dropped by
+    /// `--exclude-users-matching`. Requires an extra pass over Users.xml.
+    #[clap(long)]
+    exclude_users_posts: bool,
+    /// Only emit posts with at least this score, questions and answers alike.
+    /// Posts with an unparseable score are kept.
+    #[clap(long, value_name = "n", allow_hyphen_values = true)]
+    min_post_score: Option<i64>,
+    /// Only read the first n records of every entity, e.g. for a quick test
+    /// on a full dump. The passes that collect data beforehand, such as for
+    /// `--answer-rank`, read the same records.
+    #[clap(long, value_name = "n")]
+    limit: Option<u64>,
+    /// Only read the first n records of the whole run, in the order of the
+    /// output, so that later entities may not be read at all.
+    #[clap(long, value_name = "n")]
+    limit_total: Option<u64>,
+    /// Write `postlink.link_type` as an IRI such as `LinkedLink` or
+    /// `DuplicateLink` instead of a number, which moves to
+    /// `postlink.link_type_id`.
+    #[clap(long)]
+    link_type_names: bool,
+    /// Write `user.website_url` and `user.profile_image_url` as IRIs instead
+    /// of `xsd:anyURI` literals, so that they can be followed as links.
+    #[clap(long)]
+    urls_as_iris: bool,
+    /// Give tags node ids that keep their names readable, e.g. `tc.23` for
+    /// `c#` and `tjava` for `java`, instead of the base32 of the name.
+    #[clap(long)]
+    readable_tag_ids: bool,
+    /// Prefix the ids of a kind of node with this instead of its letter, e.g.
+    /// `--prefix post=post_` for `post_123` instead of `p123`. Prefixes that
+    /// start with one another are rejected. May be given multiple times.
+    #[clap(
+        long,
+        value_name = "entity=prefix",
+        number_of_values = 1,
+        parse(try_from_str = parse_prefix)
+    )]
+    prefix: Vec<(String, String)>,
+    /// Start every record with an `rdf:type` triple to the class of its
+    /// entity, such as `User` or `Post`.
+    #[clap(long)]
+    emit_types: bool,
+    /// Also write an `rdf:type` triple to a class such as `Question` or
+    /// `EditBody` for the `post.type` and `posthistory.type` of every post
+    /// and revision.
+    #[clap(long)]
+    type_names: bool,
+    /// Link posts, comments and revisions of deleted users, which only have a
+    /// display name, to a placeholder user with that name. Placeholders are
+    /// named by a hash of the display name, e.g. `_:ua3f1c...`.
+    #[clap(long)]
+    anonymous_owners: bool,
+    /// Drop posts that have a `DeletionDate`, with all of their triples.
+    #[clap(long)]
+    skip_deleted: bool,
+    /// Emit a `post.view_bucket` such as `100-1k` alongside the view count.
+    #[clap(long)]
+    view_buckets: bool,
+    /// Boundaries of the `--view-buckets`, in views.
+    #[clap(long, use_delimiter = true, default_value = "100,1000,10000")]
+    view_bucket_thresholds: Vec<u64>,
+    /// Also emit the year of the creation date of posts, comments and users
+    /// as an `xsd:gYear`, e.g. `post.creation_year`, for grouping by year.
+    #[clap(long)]
+    emit_year: bool,
+    /// What to do with records that cannot be parsed, e.g. at the end of a
+    /// truncated dump.
+    #[clap(long, arg_enum, default_value = "abort")]
+    on_error: OnError,
+    /// Fail on CSV files that repeat a column, instead of using the value of
+    /// its last occurrence. XML elements that repeat an attribute are always
+    /// malformed, and fail with the position of their record in the file.
+    #[clap(long)]
+    strict_attrs: bool,
+    /// Decode the attributes of XML files with this encoding, e.g.
+    /// `windows-1252` for some older dumps, instead of the one in the XML
+    /// declaration of each file, or UTF-8 if there is none.
+    #[clap(long, value_name = "label", parse(try_from_str = parse_encoding))]
+    encoding: Option<&'static Encoding>,
+    /// Warn once about every attribute of an entity that is not written, e.g.
+    /// one that was added in a newer version of the dump.
+    #[clap(long)]
+    warn_unknown_attrs: bool,
+    /// Write attributes that are not known as strings instead of dropping
+    /// them, named after the entity and attribute, e.g. `comment.x_bounty_amount`
+    /// for a `BountyAmount` of a comment.
+    #[clap(long)]
+    passthrough_unknown: bool,
+    /// Skip entities whose input file is missing, with a warning, instead of
+    /// failing. Missing CSV files are always skipped.
+    #[clap(long, default_value = "true", parse(try_from_str), value_name = "bool")]
+    skip_missing: bool,
+    /// Only convert these entities, e.g. `posts,users`.
+    #[clap(long, use_delimiter = true, parse(try_from_str = parse_entity))]
+    only: Vec<&'static str>,
+    /// Do not convert these entities, e.g. `posthistory,comments`.
+    #[clap(long, use_delimiter = true, parse(try_from_str = parse_entity))]
+    exclude: Vec<&'static str>,
+    /// Only convert records created on or after this date, e.g. `2020-01-01`
+    /// or `2020-01-01T12:00:00`. Tags have no date and are always converted.
+    #[clap(long, value_name = "date", parse(try_from_str = parse_date))]
+    since: Option<String>,
+    /// Only convert records created before this date, so that `--since` and
+    /// `--until` of consecutive windows do not overlap.
+    #[clap(long, value_name = "date", parse(try_from_str = parse_date))]
+    until: Option<String>,
+    /// Also drop records without a creation date when `--since` or `--until`
+    /// is given.
+    #[clap(long)]
+    drop_undated: bool,
+    /// Read an entity from this file instead of e.g. `Posts.xml`, such as
+    /// `--input-file posts=posts_2023.xml`. Relative paths are in the input
+    /// directory, and in archives only file names can be given. May be given
+    /// multiple times.
+    #[clap(
+        long,
+        value_name = "entity=file",
+        number_of_values = 1,
+        parse(try_from_str = parse_input_file)
+    )]
+    input_file: Vec<(&'static str, PathBuf)>,
+    /// The entity that is read from stdin when the input is `-`, e.g. `posts`.
+    /// No other entity is converted.
+    #[clap(long, value_name = "entity", parse(try_from_str = parse_entity))]
+    stdin_entity: Option<&'static str>,
+    /// Write the entities in parallel, one thread each. Only useful when
+    /// compression is not the bottleneck, see the README.
+    #[clap(long, arg_enum, value_name = "strategy")]
+    parallel: Option<Parallel>,
+    /// Number of threads for `--parallel`, at most one per entity. Defaults to
+    /// one per entity, and with `--parallel records` to one per core.
+    #[clap(long, requires = "parallel")]
+    jobs: Option<usize>,
+    /// Do not show progress bars, and log the number of records written
+    /// instead. Bars are only shown when stderr is a terminal.
+    #[clap(long)]
+    no_progress: bool,
+    /// Log more: debug messages with `-v`, and trace messages with `-vv`.
+    /// Dependencies only log warnings, unless `RUST_LOG` is set, which
+    /// overrides `-v` and `-q`.
+    #[clap(short, long, parse(from_occurrences))]
+    verbose: u64,
+    /// Log less: only warnings and errors with `-q`, and only errors with
+    /// `-qq`.
+    #[clap(short, long, parse(from_occurrences), conflicts_with = "verbose")]
+    quiet: u64,
+    /// Also emit the `AboutMe` HTML of users as plain text, as
+    /// `user.about_me_text`.
+    #[clap(long)]
+    about_me_text: bool,
+    /// Strip personal data: the website, location, about me and profile image
+    /// of users are dropped and their display names replaced by a hash of
+    /// their id, and the display names of deleted users on posts, comments and
+    /// post history are dropped.
+    #[clap(long)]
+    anonymize: bool,
+    /// Also emit the names of the tags of a post, as `post.tag_name`, next to
+    /// the `post.tags` edges.
+    #[clap(long)]
+    inline_tags: bool,
+    /// Tag the free text of posts, comments, post history and users with this
+    /// language, e.g. `en`.
+    #[clap(long, value_name = "tag", parse(try_from_str = parse_lang))]
+    lang: Option<String>,
+    /// Only emit statistics about the output, such as the number of posts and
+    /// users and the number of posts per tag and per user, instead of the
+    /// records themselves. See the README for the predicates.
+    #[clap(long)]
+    output_stats_only: bool,
+    /// Skip comments on posts that are not emitted, e.g. because they are
+    /// missing from a partial dump or were filtered out. Requires an extra
+    /// pass over Posts.xml and a set of all post ids in memory.
+    #[clap(long)]
+    drop_dangling_comments: bool,
+    /// Naming style of the predicates after the entity, e.g. `post.viewCount`
+    /// with `camel`. The schemas in `res/` use `snake`.
+    #[clap(long, arg_enum, default_value = "snake")]
+    predicate_style: PredicateStyle,
+    /// Base IRI of the predicates, e.g. `post.score` becomes
+    /// `https://stackoverflow.com/schema#post.score` by default. An empty base
+    /// keeps predicates relative, as the Dgraph schemas in `res/` expect; see
+    /// the README for the formats that ignore it.
+    #[clap(
+        long,
+        value_name = "iri",
+        default_value = PREDICATE_NAMESPACE,
+        setting = ArgSettings::AllowEmptyValues,
+        parse(try_from_str = parse_predicate_base)
+    )]
+    predicate_base: String,
+    /// Write the entities as IRIs under this base instead of as blank nodes,
+    /// e.g. `https://example.org/so/post/123` for `_:p123`, so that outputs
+    /// of separate runs can refer to each other.
+    #[clap(long, value_name = "iri", parse(try_from_str = parse_base_iri))]
+    base_iri: Option<String>,
+    /// Emit the `post.answer_rank` of every answer among the answers to its
+    /// question, by score, and whether it is the accepted one as
+    /// `post.is_accepted`. Requires an extra pass over Posts.xml.
+    #[clap(long)]
+    answer_rank: bool,
+    /// Convert `\r\n` and `\r` line endings within post bodies, comment and
+    /// history texts and user profiles to `\n`.
+    #[clap(long)]
+    normalize_line_endings_in_text: bool,
+    /// Also emit a `user.badge` edge from every user to each of their badges.
+    #[clap(long)]
+    user_badge_edges: bool,
+    /// Also write the reverse of every relation, e.g. `post.comment` from a
+    /// post to each of its comments next to their `comment.post`, so that
+    /// queries can follow relations in both directions.
+    #[clap(long)]
+    emit_inverse: bool,
+    /// Exit with an error if no triples were written at all, e.g. because the
+    /// input files are empty or every record was filtered out.
+    #[clap(long)]
+    fail_on_empty: bool,
+    /// Exit with an error if no triples were written for any one entity,
+    /// including entities whose input file is missing.
+    #[clap(long)]
+    fail_on_empty_entity: bool,
 }
 
-fn write_rdf<W: Write, P: AsRef<Path>>(
-    name: &str,
-    formatter: &mut NTriplesFormatter<W>,
-    writer: impl Fn(&mut NTriplesFormatter<W>, &BytesStart) -> Result<()>,
-    path: P,
+impl Opts {
+    /// Whether a record created at `date` falls outside `--since` and
+    /// `--until`. Records without a date are kept unless `--drop-undated`.
+    fn outside_window(&self, date: Option<&str>) -> bool {
+        if self.since.is_none() && self.until.is_none() {
+            return false;
+        }
+        match date {
+            Some(date) => {
+                self.since
+                    .as_ref()
+                    .is_some_and(|since| date < since.as_str())
+                    || self
+                        .until
+                        .as_ref()
+                        .is_some_and(|until| date >= until.as_str())
+            }
+            None => self.drop_undated,
+        }
+    }
+
+    /// Whether the entity is converted, according to `--only`, `--exclude` and
+    /// `--stdin-entity`.
+    fn selects(&self, entity: &str) -> bool {
+        if self
+            .stdin_entity
+            .is_some_and(|stdin_entity| stdin_entity != entity)
+        {
+            return false;
+        }
+        (self.only.is_empty() || self.only.contains(&entity)) && !self.exclude.contains(&entity)
+    }
+
+    /// The `--compression-level`, clamped to the levels of the compression.
+    fn compression_level(&self) -> u32 {
+        let levels = self.compression.levels();
+        let level = self
+            .compression_level
+            .unwrap_or_else(|| self.compression.default_level());
+        level.clamp(*levels.start(), *levels.end())
+    }
+
+    /// Whether the input file of an entity is missing and should be skipped.
+    /// Data Explorer exports are made one table at a time, so it is normal
+    /// for most CSV files to be missing.
+    fn skips_missing(&self, path: &Path) -> Result<bool> {
+        let skips = self.input_format == InputFormat::Csv || self.skip_missing;
+        Ok(skips && !input_exists(path)?)
+    }
+
+    /// The output file of an entity with `--split`, e.g. `<dir>/posts.nt.gz`.
+    fn entity_output(&self, entity: &str) -> PathBuf {
+        let mut name = format!("{}.{}", entity.to_lowercase(), self.format.extension());
+        if let Some(extension) = self.compression.extension() {
+            name.push('.');
+            name.push_str(extension);
+        }
+        self.path_to.join(name)
+    }
+
+    /// How every pass reads the input files.
+    fn read_options(&self) -> ReadOptions {
+        ReadOptions {
+            format: self.input_format,
+            on_error: self.on_error,
+            mmap: self.mmap,
+            buffer_size: self.buffer_size(),
+            strict_attrs: self.strict_attrs,
+            limit: self.limit,
+            encoding: self.encoding,
+        }
+    }
+
+    /// The level of the messages of stackoverflow2rdf itself, by `-v` and
+    /// `-q`.
+    fn log_level(&self) -> LevelFilter {
+        match (self.quiet, self.verbose) {
+            (0, 0) => LevelFilter::Info,
+            (0, 1) => LevelFilter::Debug,
+            (0, _) => LevelFilter::Trace,
+            (1, _) => LevelFilter::Warn,
+            _ => LevelFilter::Error,
+        }
+    }
+
+    /// The number of threads that convert records with `--parallel records`.
+    fn record_jobs(&self) -> usize {
+        let cores = || thread::available_parallelism().map_or(1, |cores| cores.get());
+        self.jobs.unwrap_or_else(cores).max(1)
+    }
+
+    /// The size of the read and write buffers, in bytes.
+    fn buffer_size(&self) -> usize {
+        self.read_buffer.max(1) * 1024
+    }
+
+    /// The input file of an entity, e.g. `<dir>/Posts.xml`, unless it is
+    /// overridden with `--input-file`.
+    fn input_path(&self, entity: &str) -> PathBuf {
+        if is_stdin(&self.path_from) {
+            return self.path_from.clone();
+        }
+        match self
+            .input_file
+            .iter()
+            .rev()
+            .find(|&&(known, _)| known == entity)
+        {
+            Some((_, file)) => self.path_from.join(file),
+            None => self
+                .path_from
+                .join(entity)
+                .with_extension(self.input_format.extension()),
+        }
+    }
+
+    /// Whether `value` was configured with `--sentinel` to mean "no value" for
+    /// `predicate`.
+    fn is_sentinel(&self, predicate: &str, value: &str) -> bool {
+        self.sentinel
+            .iter()
+            .any(|(p, v)| p == predicate && v == value)
+    }
+
+    /// Applies `--normalize-line-endings-in-text` to a text field.
+    fn normalize_text<'a>(&self, text: Cow<'a, str>) -> Cow<'a, str> {
+        if self.normalize_line_endings_in_text {
+            normalize_line_endings(text)
+        } else {
+            text
+        }
+    }
+
+    fn lang(&self) -> Option<&str> {
+        self.lang.as_deref()
+    }
+
+    /// The prefix of the ids of a kind of node, e.g. `p` for `post`, unless it
+    /// is overridden with `--prefix`.
+    fn prefix(&self, kind: &str) -> &str {
+        match self.prefix.iter().rev().find(|(known, _)| known == kind) {
+            Some((_, prefix)) => prefix,
+            None => {
+                PREFIXES
+                    .iter()
+                    .find(|&&(known, _)| known == kind)
+                    .unwrap()
+                    .1
+            }
+        }
+    }
+
+    /// Every kind of node with the prefix of its ids.
+    fn prefixes(&self) -> Vec<(&'static str, &str)> {
+        PREFIXES
+            .iter()
+            .map(|&(kind, _)| (kind, self.prefix(kind)))
+            .collect()
+    }
+
+    /// The node id of a tag, which must be the same wherever the tag is
+    /// referenced.
+    fn tag_id(&self, name: &str) -> String {
+        if self.readable_tag_ids {
+            readable_tag_id(self.prefix("tag"), name)
+        } else {
+            tag_id(self.prefix("tag"), name)
+        }
+    }
+
+    /// The value configured with `--default` for `predicate`, if any.
+    fn default_value(&self, predicate: &str) -> Option<Cow<'_, str>> {
+        self.default
+            .iter()
+            .find(|(p, _)| p == predicate)
+            .map(|(_, v)| Cow::Borrowed(v.as_str()))
+    }
+}
+
+/// Checks that the options can be used when reading from stdin, which can
+/// only be read once.
+fn check_stdin(opts: &Opts) -> Result<()> {
+    if opts.stdin_entity.is_none() {
+        bail!("reading from stdin requires --stdin-entity");
+    }
+    let passes = [
+        ("--posts-only-with-accepted", opts.posts_only_with_accepted),
+        ("--drop-dangling-comments", opts.drop_dangling_comments),
+        ("--editor-edges", opts.editor_edges),
+        ("--answer-rank", opts.answer_rank),
+        ("--ego", opts.ego.is_some()),
+        ("--exclude-users-posts", opts.exclude_users_posts),
+    ];
+    for &(flag, set) in &passes {
+        if set {
+            bail!(
+                "{} needs an extra pass over the input, which stdin does not allow",
+                flag
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Parses a `--lang` tag such as `en` or `pt-BR`.
+fn parse_lang(s: &str) -> Result<String> {
+    let mut subtags = s.split('-');
+    let primary = subtags.next().unwrap_or_default();
+    let valid = (1..=8).contains(&primary.len())
+        && primary.bytes().all(|b| b.is_ascii_alphabetic())
+        && subtags.all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.bytes().all(|b| b.is_ascii_alphanumeric())
+        });
+    if !valid {
+        bail!("expected a language tag such as `en` or `pt-BR`");
+    }
+    Ok(s.to_string())
+}
+
+/// Parses a `--since` or `--until` date, which is compared as a string with
+/// the dates of the dump, so it cannot have a time zone.
+fn parse_date(s: &str) -> Result<String> {
+    let datetime = if s.contains('T') {
+        s.to_string()
+    } else {
+        format!("{}T00:00:00", s)
+    };
+    let (_, time) = datetime.split_once('T').unwrap_or_default();
+    if s.starts_with('-') || !is_datetime(&datetime) || time.contains(['Z', '+', '-']) {
+        bail!("expected a date such as `2020-01-31`, or a date and time without a time zone");
+    }
+    Ok(s.to_string())
+}
+
+/// Parses a `--predicate-base`, which must be absolute unless it is empty.
+fn parse_predicate_base(s: &str) -> Result<String> {
+    if !s.is_empty() && !s.contains(':') {
+        bail!("expected an absolute IRI such as `https://example.org/schema#`, or an empty one");
+    }
+    Ok(s.to_string())
+}
+
+/// Parses an entity name for `--only` and `--exclude`, e.g. `posts` or
+/// `PostHistory`.
+fn parse_entity(s: &str) -> Result<&'static str> {
+    match WRITERS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(s))
+    {
+        Some(&(name, _)) => Ok(name),
+        None => {
+            let names = WRITERS
+                .iter()
+                .map(|(name, _)| name.to_ascii_lowercase())
+                .collect::<Vec<_>>();
+            bail!(
+                "unknown entity `{}`, expected one of: {}",
+                s,
+                names.join(", ")
+            )
+        }
+    }
+}
+
+/// Parses an `--input-file`, e.g. `posts=posts_2023.xml`.
+fn parse_input_file(s: &str) -> Result<(&'static str, PathBuf)> {
+    let (entity, file) = s
+        .split_once('=')
+        .context("expected a value of the form `entity=file`")?;
+    if file.is_empty() {
+        bail!("expected a file name after `=`");
+    }
+    Ok((parse_entity(entity)?, PathBuf::from(file)))
+}
+
+/// Checks that the options do not concatenate outputs of a format that
+/// cannot be concatenated.
+fn check_concatenation(opts: &Opts) -> Result<()> {
+    if opts.format.concatenates() || opts.dry_run {
+        return Ok(());
+    }
+    let option = if opts.merge {
+        "--merge"
+    } else if opts.parallel == Some(Parallel::Split) && !opts.split {
+        "--parallel split"
+    } else if opts.resume && !opts.split {
+        "--resume"
+    } else {
+        return Ok(());
+    };
+    bail!(
+        "rdfxml, jsonld and arrow-ipc outputs are single documents that cannot be concatenated, \
+         so they cannot be combined with {}",
+        option
+    );
+}
+
+fn parse_predicate_value(s: &str) -> Result<(String, String)> {
+    let (predicate, value) = s
+        .split_once('=')
+        .context("expected a value of the form `predicate=value`")?;
+    Ok((predicate.to_string(), value.to_string()))
+}
+
+/// State shared by the `write_*` functions for the duration of a run.
+struct State<'a> {
+    opts: &'a Opts,
+    /// Ids of accepted answers, collected in a first pass over Posts.xml when
+    /// `--posts-only-with-accepted` is set.
+    accepted_answers: Option<&'a HashSet<u64>>,
+    /// Ids of the posts that are emitted, collected in a first pass over
+    /// Posts.xml when `--drop-dangling-comments` is set.
+    post_ids: Option<&'a HashSet<u64>>,
+    /// Owner of every post, collected in a first pass over Posts.xml when
+    /// `--editor-edges` is set.
+    post_owners: Option<&'a HashMap<u64, i64>>,
+    /// Ranks of the answers when `--answer-rank` is set.
+    answer_ranks: Option<&'a AnswerRanks>,
+    /// Ids of the entities to emit when `--ego` is set.
+    ego: Option<&'a HashSet<String>>,
+    /// Ids of the users dropped by `--exclude-users-matching`, collected in a
+    /// first pass over Users.xml when `--exclude-users-posts` is set.
+    excluded_users: Option<&'a HashSet<String>>,
+    /// Progress bars, unless `--no-progress` is set or stderr is not a
+    /// terminal.
+    progress: Option<&'a Progress>,
+    /// Entities that were finished by an earlier run when `--resume` is set.
+    checkpoint: Option<&'a Checkpoint>,
+    /// Per-tag output files when `--posts-by-tag` is set.
+    posts_by_tag: Option<TagPartitions<'a>>,
+    /// Tag pair counts accumulated during the Posts pass when
+    /// `--tag-cooccurrence` is set.
+    tag_cooccurrence: Option<TagCooccurrence>,
+    /// Attributes per entity that were reported by `--warn-unknown-attrs`.
+    unknown_attributes: HashMap<&'static str, HashSet<Vec<u8>>>,
+    /// Records that are left to read when `--limit-total` is set, which is
+    /// only used by a single state.
+    remaining_records: Option<u64>,
+    /// Triples and predicates that were written when `--emit-void` or
+    /// `--emit-predicates` is set.
+    output_stats: Option<OutputStats>,
+    warnings: Warnings,
+    /// Number of records and triples per entity.
+    counts: Vec<EntityCount>,
+}
+
+impl<'a> State<'a> {
+    fn into_report(self) -> Report {
+        Report {
+            warnings: self.warnings,
+            counts: self.counts,
+            output_stats: self.output_stats.unwrap_or_default(),
+        }
+    }
+
+    /// The counts of an entity that was finished by an earlier run, which is
+    /// not written again with `--resume`.
+    fn resumed(&self, name: &str) -> Option<Report> {
+        let counts = self.checkpoint?.finished(name)?;
+        info!("{}: finished by an earlier run, skipping", name);
+        Some(Report {
+            warnings: Warnings::default(),
+            counts,
+            output_stats: OutputStats::default(),
+        })
+    }
+
+    /// Records an entity that was written completely in the `--resume`
+    /// checkpoint. An entity that was cut short by `--timeout` is not.
+    fn checkpoint(&self, name: &str) -> Result<()> {
+        match self.checkpoint {
+            Some(checkpoint) if !stop::requested() => checkpoint.finish(name, &self.counts),
+            _ => Ok(()),
+        }
+    }
+
+    /// Warns about an attribute that is not known, for `--warn-unknown-attrs`,
+    /// once per entity and attribute. Returns whether it is written anyway,
+    /// with `--passthrough-unknown`.
+    fn unknown_attribute(&mut self, entity: &'static str, key: &[u8]) -> bool {
+        let passthrough = self.opts.passthrough_unknown;
+        if !self.opts.warn_unknown_attrs {
+            return passthrough;
+        }
+        let seen = self.unknown_attributes.entry(entity).or_default();
+        if seen.contains(key) {
+            return passthrough;
+        }
+        seen.insert(key.to_vec());
+        self.warnings.warn(
+            Category::UnknownAttribute,
+            format_args!(
+                "{}: unknown attribute `{}`, {}",
+                entity,
+                String::from_utf8_lossy(key),
+                if passthrough {
+                    "passing it through"
+                } else {
+                    "ignoring it"
+                }
+            ),
+        );
+        passthrough
+    }
+
+    /// Whether the entity with this id falls outside the `--ego` network.
+    fn excludes(&self, id: &str) -> bool {
+        match self.ego {
+            Some(ego) => !ego.contains(id),
+            None => false,
+        }
+    }
+
+    /// A state for a thread that converts records of the same entity with
+    /// `--parallel records`. It only collects warnings, which are merged back
+    /// at the end.
+    fn fork(&self) -> State<'a> {
+        State {
+            opts: self.opts,
+            accepted_answers: self.accepted_answers,
+            post_ids: self.post_ids,
+            post_owners: self.post_owners,
+            answer_ranks: self.answer_ranks,
+            ego: self.ego,
+            excluded_users: self.excluded_users,
+            progress: self.progress,
+            checkpoint: self.checkpoint,
+            posts_by_tag: None,
+            tag_cooccurrence: None,
+            unknown_attributes: HashMap::new(),
+            remaining_records: None,
+            output_stats: None,
+            warnings: Warnings::default(),
+            counts: Vec::new(),
+        }
+    }
+
+    /// Whether a post or comment by this user is dropped by
+    /// `--exclude-users-posts`.
+    fn excludes_author(&self, user_id: Option<&str>) -> bool {
+        match (self.excluded_users, user_id) {
+            (Some(excluded_users), Some(user_id)) => excluded_users.contains(user_id),
+            _ => false,
+        }
+    }
+
+    /// Splits the state into one per entity of `WRITERS`, so that they can be
+    /// written in parallel. Only the Posts writer uses the per-tag outputs and
+    /// the tag co-occurrence counts.
+    fn split(mut self) -> Vec<State<'a>> {
+        WRITERS
+            .iter()
+            .map(|&(name, _)| {
+                let (posts_by_tag, tag_cooccurrence) = if name == "Posts" {
+                    (self.posts_by_tag.take(), self.tag_cooccurrence.take())
+                } else {
+                    (None, None)
+                };
+                State {
+                    opts: self.opts,
+                    accepted_answers: self.accepted_answers,
+                    post_ids: self.post_ids,
+                    post_owners: self.post_owners,
+                    answer_ranks: self.answer_ranks,
+                    ego: self.ego,
+                    excluded_users: self.excluded_users,
+                    progress: self.progress,
+                    checkpoint: self.checkpoint,
+                    posts_by_tag,
+                    tag_cooccurrence,
+                    unknown_attributes: HashMap::new(),
+                    remaining_records: self.remaining_records,
+                    output_stats: self.output_stats.as_ref().map(|_| OutputStats::default()),
+                    warnings: Warnings::default(),
+                    counts: Vec::new(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Number of records read and triples written for an entity. The triples of
+/// derived data that is written after an entity, such as the tag
+/// co-occurrences, are counted under a name of their own.
+struct EntityCount {
+    name: &'static str,
+    records: u64,
+    /// Records that were read but not converted: malformed ones that were
+    /// skipped with `--on-error skip`, and those dropped by a filter.
+    skipped: u64,
+    triples: u64,
+}
+
+/// What the writers report back at the end of a run.
+#[derive(Default)]
+struct Report {
+    warnings: Warnings,
+    counts: Vec<EntityCount>,
+    /// Triples and predicates that were written, for `--emit-void` and
+    /// `--emit-predicates`.
+    output_stats: OutputStats,
+}
+
+impl Report {
+    fn merge(&mut self, other: Report) {
+        self.warnings.merge(&other.warnings);
+        self.counts.extend(other.counts);
+        self.output_stats.merge(other.output_stats);
+    }
+
+    /// Number of records read for an entity.
+    fn records(&self, name: &str) -> u64 {
+        self.counts
+            .iter()
+            .filter(|count| count.name == name)
+            .map(|count| count.records)
+            .sum()
+    }
+
+    /// Number of triples written for an entity, not counting derived data.
+    fn triples(&self, name: &str) -> u64 {
+        self.counts
+            .iter()
+            .filter(|count| count.name == name)
+            .map(|count| count.triples)
+            .sum()
+    }
+
+    /// Number of records of an entity that were not converted.
+    fn skipped(&self, name: &str) -> u64 {
+        self.counts
+            .iter()
+            .filter(|count| count.name == name)
+            .map(|count| count.skipped)
+            .sum()
+    }
+
+    /// Logs the number of records per entity that were read but not
+    /// converted, next to the summary of the warnings.
+    fn log_skipped(&self) {
+        let skipped = WRITERS
+            .iter()
+            .map(|&(name, _)| (name, self.skipped(name)))
+            .filter(|&(_, skipped)| skipped != 0)
+            .collect::<Vec<_>>();
+        if skipped.is_empty() {
+            info!("Skipped records: none");
+            return;
+        }
+
+        info!("Skipped records:");
+        for (name, skipped) in skipped {
+            info!("  {:<24} {:>12}", name, skipped);
+        }
+    }
+
+    /// Writes the number of records per entity, e.g. `"posts": 678`, the
+    /// number of those that were not converted under `skipped`, the number of
+    /// triples per entity under `triples`, and the total number of triples
+    /// including derived data as `triples_total`, for `--stats-json`. Entities
+    /// that were skipped count as 0.
+    fn write_json(&self, path: &Path) -> Result<()> {
+        let mut stats = Map::new();
+        let mut skipped = Map::new();
+        let mut triples = Map::new();
+        for &(name, _) in WRITERS {
+            let key = name.to_ascii_lowercase();
+            stats.insert(key.clone(), Value::from(self.records(name)));
+            skipped.insert(key.clone(), Value::from(self.skipped(name)));
+            triples.insert(key, Value::from(self.triples(name)));
+        }
+        stats.insert("skipped".to_string(), Value::from(skipped));
+        stats.insert("triples".to_string(), Value::from(triples));
+        let total = self.counts.iter().map(|count| count.triples).sum::<u64>();
+        stats.insert("triples_total".to_string(), Value::from(total));
+
+        let file = File::create(path).context("could not create stats file")?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &stats)
+            .context("could not write stats file")
+    }
+}
+
+type Writer = fn(&mut State, &mut dyn Formatter, &Row) -> Result<()>;
+
+/// The entities, in the order they are written to the output.
+const WRITERS: &[(&str, Writer)] = &[
+    ("Badges", write_badge),
+    ("Comments", write_comment),
+    ("Posts", write_post),
+    ("PostHistory", write_posthistory),
+    ("PostLinks", write_postlink),
+    ("Tags", write_tag),
+    ("Users", write_user),
+    ("Votes", write_vote),
+];
+
+type SharedFormatter = Mutex<Box<dyn Formatter + Send>>;
+
+#[derive(ArgEnum, Clone, Copy, PartialEq)]
+enum Parallel {
+    /// All entities are written to the output file, which is locked for every
+    /// record.
+    Shared,
+    /// Every entity is written to a temporary file next to the output, and the
+    /// files are concatenated at the end.
+    Split,
+    /// The entities are written one after the other, and the records of each
+    /// are converted on `--jobs` threads, for dumps in which one file dwarfs
+    /// the others.
+    Records,
+}
+
+fn open_formatter(opts: &Opts, path: &Path, append: bool) -> Result<SharedFormatter> {
+    let level = opts.compression_level();
+    let (buffer, threads) = (opts.buffer_size(), opts.compress_threads.max(1));
+    let formatter = match &opts.store {
+        #[cfg(feature = "store")]
+        Some(dir) if !opts.dry_run => rewrite_triples(
+            opts,
+            Box::new(StoreFormatter::open(dir, &opts.predicate_base)?),
+        ),
+        _ => {
+            let output_writer = if opts.dry_run {
+                Encoder::None(BufWriter::new(Box::new(io::sink())))
+            } else {
+                open_output(path, opts.compression, level, append, buffer, threads)
+                    .with_context(|| format!("Could not create output file {}", path.display()))?
+            };
+            output_formatter(opts, output_writer)?
+        }
+    };
+    if opts.output_stats_only {
+        return Ok(Mutex::new(Box::new(StatsFormatter::new(formatter))));
+    }
+    Ok(Mutex::new(formatter))
+}
+
+/// Creates the formatter of an output, with the options that rewrite the
+/// triples applied. Also used for the `--posts-by-tag` outputs.
+fn output_formatter(opts: &Opts, write: Encoder) -> Result<Box<dyn Formatter + Send>> {
+    let formatter = new_formatter(opts.format, write, &opts.predicate_base)?;
+    Ok(rewrite_triples(opts, formatter))
+}
+
+/// Applies the options that rewrite the triples to a formatter.
+fn rewrite_triples(
+    opts: &Opts,
+    mut formatter: Box<dyn Formatter + Send>,
+) -> Box<dyn Formatter + Send> {
+    if !opts.keep_control_chars {
+        formatter = Box::new(StripControlChars::new(formatter));
+    }
+    // The protobuf fields are named by `res/stackoverflow.proto`.
+    if opts.predicate_style != PredicateStyle::Snake && opts.format != OutputFormat::Protobuf {
+        formatter = Box::new(StyledFormatter::new(formatter, opts.predicate_style));
+    }
+    if let Some(base_iri) = &opts.base_iri {
+        formatter = Box::new(NodeIris::new(formatter, base_iri.clone(), &opts.prefixes()));
+    }
+    if let Some(dedup) = opts.dedup {
+        formatter = Box::new(Deduplicate::new(formatter, dedup, opts.dedup_memory));
+    }
+    formatter
+}
+
+/// Writes the entities one after the other.
+fn write_sequential(mut state: State) -> Result<Report> {
+    let output = open_formatter(state.opts, &state.opts.path_to, state.opts.merge)?;
+    let result = WRITERS
+        .iter()
+        .try_for_each(|&(name, writer)| write_entity(name, &mut state, &output, writer));
+    finish_output(output, result)?;
+    Ok(state.into_report())
+}
+
+/// Writes every entity on its own thread, into the same output file. Records
+/// of different entities are interleaved.
+fn write_shared(state: State) -> Result<Report> {
+    let opts = state.opts;
+    let output = open_formatter(opts, &opts.path_to, opts.merge)?;
+
+    let output_ref = &output;
+    let tasks = state
+        .split()
+        .into_iter()
+        .zip(WRITERS)
+        .map(|(mut state, &(name, writer))| {
+            Box::new(move || {
+                write_entity(name, &mut state, output_ref, writer)?;
+                Ok(state.into_report())
+            }) as Task
+        })
+        .collect();
+    let report = run_tasks(opts, tasks);
+    finish_output(output, report)
+}
+
+/// Finishes the output, even when writing it failed, so that the records that
+/// were written end up in a complete file: the last zstd frame is written, and
+/// formats such as RDF/XML are closed. The error of the writing comes first.
+fn finish_output<T>(output: SharedFormatter, result: Result<T>) -> Result<T> {
+    let finished = output.into_inner().unwrap().finish();
+    let value = result?;
+    finished?;
+    Ok(value)
+}
+
+/// Writes every entity on its own thread, into a temporary file each, then
+/// concatenates the files in the usual order. Most output formats are streams,
+/// and gzip members and zstd frames can be concatenated, so the result is a
+/// valid output.
+///
+/// With `--split`, the files are the outputs of the entities instead, and are
+/// kept. Without `--parallel`, they are then written one after the other, which
+/// is also how `--resume` writes a single output.
+fn write_split(state: State) -> Result<Report> {
+    let opts = state.opts;
+    if opts.split {
+        let tasks = state
+            .split()
+            .into_iter()
+            .zip(WRITERS)
+            .map(|(mut state, &(name, writer))| {
+                Box::new(move || {
+                    if let Some(report) = state.resumed(name) {
+                        return Ok(report);
+                    }
+                    // Skipped entities get no file at all.
+                    if !opts.selects(name) {
+                        info!("{}: skipped", name);
+                        return Ok(state.into_report());
+                    }
+                    let output = open_formatter(opts, &opts.entity_output(name), opts.merge)?;
+                    let result = write_entity(name, &mut state, &output, writer);
+                    finish_output(output, result)?;
+                    state.checkpoint(name)?;
+                    Ok(state.into_report())
+                }) as Task
+            })
+            .collect();
+        return run_tasks(opts, tasks);
+    }
+
+    let parts = WRITERS
+        .iter()
+        .map(|(name, _)| {
+            // Parts of stdout go to the temporary directory instead.
+            let mut part = if is_stdout(&opts.path_to) {
+                let name = format!("stackoverflow2rdf-{}", process::id());
+                env::temp_dir().join(name).into_os_string()
+            } else {
+                opts.path_to.clone().into_os_string()
+            };
+            part.push(format!(".{}.part", name));
+            PathBuf::from(part)
+        })
+        .collect::<Vec<_>>();
+
+    let tasks = state
+        .split()
+        .into_iter()
+        .zip(WRITERS)
+        .zip(&parts)
+        .map(|((mut state, &(name, writer)), part)| {
+            Box::new(move || {
+                if let Some(report) = state.resumed(name) {
+                    return Ok(report);
+                }
+                let output = open_formatter(opts, part, false)?;
+                let result = write_entity(name, &mut state, &output, writer);
+                finish_output(output, result)?;
+                state.checkpoint(name)?;
+                Ok(state.into_report())
+            }) as Task
+        })
+        .collect();
+    let report = run_tasks(opts, tasks)?;
+
+    if opts.resume && stop::requested() {
+        info!("Run was stopped, keeping the parts for --resume");
+        return Ok(report);
+    }
+
+    concatenate(&parts, &opts.path_to, opts.merge).context("Could not concatenate output files")?;
+    Ok(report)
+}
+
+/// Writes an entity on a thread of `run_tasks`.
+type Task<'a> = Box<dyn FnOnce() -> Result<Report> + Send + 'a>;
+
+/// Runs the tasks of `WRITERS` on `--jobs` threads, and merges their reports.
+/// Larger input files take longer, so their entities are started first.
+fn run_tasks(opts: &Opts, tasks: Vec<Task>) -> Result<Report> {
+    let mut queue = tasks.into_iter().enumerate().collect::<Vec<_>>();
+    queue.sort_by_key(|(idx, _)| {
+        let path = opts.input_path(WRITERS[*idx].0);
+        Reverse(input_size(&path).unwrap_or(0))
+    });
+    let jobs = match opts.parallel {
+        Some(Parallel::Shared | Parallel::Split) => opts.jobs.unwrap_or(queue.len()),
+        Some(Parallel::Records) | None => 1,
+    };
+    let jobs = jobs.clamp(1, queue.len().max(1));
+    let queue = Mutex::new(queue.into_iter());
+
+    let mut results = thread::scope(|scope| {
+        let handles = (0..jobs)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = Vec::new();
+                    loop {
+                        let next = queue.lock().unwrap().next();
+                        match next {
+                            Some((idx, task)) => results.push((idx, task())),
+                            None => return results,
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("writer thread panicked"))
+            .collect::<Vec<_>>()
+    });
+
+    results.sort_by_key(|(idx, _)| *idx);
+    let mut report = Report::default();
+    for (_, result) in results {
+        report.merge(result?);
+    }
+    Ok(report)
+}
+
+/// Writes an entity, along with the data derived from it.
+fn write_entity(
+    name: &'static str,
+    state: &mut State,
+    output: &SharedFormatter,
+    writer: Writer,
 ) -> Result<()> {
-    info!("{}: started", name);
-    let mut buf = Vec::new();
-
-    let mut reader = Reader::from_file(path)?;
-    reader.trim_text(true);
-
-    let mut count = 0usize;
-    loop {
-        match reader.read_event(&mut buf) {
-            Ok(Event::Empty(e)) => {
-                writer(formatter, &e)?;
-                count += 1;
-                if count % 100000 == 0 {
-                    info!("{}: count: {}", name, count);
+    write_rdf(name, state, output, writer)?;
+
+    if name == "Posts" {
+        if let Some(posts_by_tag) = state.posts_by_tag.take() {
+            posts_by_tag.finish()?;
+        }
+
+        if let Some(tag_cooccurrence) = state.tag_cooccurrence.take() {
+            let mut output = output.lock().unwrap();
+            output.set_graph(name);
+            let mut counted;
+            let formatter: &mut dyn Formatter = match &mut state.output_stats {
+                Some(stats) => {
+                    counted = OutputCounter::new(output.as_mut(), stats);
+                    &mut counted
                 }
-            }
-            Ok(Event::Eof) => break,
-            Ok(_) => (),
-            Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
+                None => output.as_mut(),
+            };
+            let mut inverses;
+            let formatter: &mut dyn Formatter = if state.opts.emit_inverse {
+                inverses = Inverses::new(formatter);
+                &mut inverses
+            } else {
+                formatter
+            };
+            let mut counter = Counter::new(formatter);
+            write_tag_cooccurrence(state.opts, &mut counter, tag_cooccurrence)?;
+            state.counts.push(EntityCount {
+                name: "TagCooccurrence",
+                records: 0,
+                skipped: 0,
+                triples: counter.count,
+            });
         }
-        buf.clear();
     }
 
+    Ok(())
+}
+
+fn write_rdf<'a>(
+    name: &'static str,
+    state: &mut State<'a>,
+    output: &SharedFormatter,
+    writer: Writer,
+) -> Result<()> {
+    if !state.opts.selects(name) {
+        info!("{}: skipped", name);
+        return Ok(());
+    }
+
+    let path = state.opts.input_path(name);
+    if state.opts.skips_missing(&path)? {
+        state.warnings.warn(
+            Category::MissingInput,
+            format_args!("{}: {} not found, skipping", name, path.display()),
+        );
+        return Ok(());
+    }
+
+    if stop::requested() {
+        info!("{}: skipped, run was stopped", name);
+        return Ok(());
+    }
+
+    let mut read = state.opts.read_options();
+    if let Some(remaining) = state.remaining_records {
+        if remaining == 0 {
+            info!("{}: skipped, --limit-total was reached", name);
+            return Ok(());
+        }
+        read.limit = Some(read.limit.map_or(remaining, |limit| limit.min(remaining)));
+    }
+
+    info!("{}: started", name);
+
+    let reify = if state.opts.reify.is_empty() {
+        None
+    } else {
+        let source = path.file_name().unwrap_or_default().to_string_lossy();
+        let prefix = state.opts.prefix("statement").to_string();
+        Some(Reify::new(source.into_owned(), &state.opts.reify, prefix))
+    };
+
+    // Files in an archive have no size of their own, so they get a spinner.
+    let bar = state
+        .progress
+        .map(|progress| progress.bar(name, input_size(&path)));
+
+    let mut tally = Tally {
+        name,
+        bar: bar.as_ref(),
+        count: 0,
+        triples: 0,
+        skipped: 0,
+    };
+    let mut output_stats = state.output_stats.take();
+    let (on_error, emit_inverse) = (state.opts.on_error, state.opts.emit_inverse);
+    let malformed = if state.opts.parallel == Some(Parallel::Records) {
+        let workers = (0..state.opts.record_jobs())
+            .map(|_| state.fork())
+            .collect();
+        let convert = |state: &mut State<'a>, formatter: &mut dyn Formatter, row: &Row| {
+            convert_record(state, name, writer, formatter, row)
+        };
+        let (malformed, workers) =
+            convert_in_parallel(read, &path, bar.as_ref(), workers, convert, |record| {
+                let Converted {
+                    position,
+                    triples,
+                    result,
+                } = record;
+                let mut output = output.lock().unwrap();
+                output.set_graph(name);
+                let (result, written, failed) = write_record(
+                    output.as_mut(),
+                    output_stats.as_mut(),
+                    reify.as_ref(),
+                    emit_inverse,
+                    |counter| triples.replay(counter).and(result),
+                );
+                drop(output);
+                let warnings = &mut state.warnings;
+                tally.record(warnings, on_error, position, result, written, failed)
+            })?;
+        for worker in workers {
+            state.warnings.merge(&worker.warnings);
+        }
+        malformed
+    } else {
+        for_each_tracked_row(read, &path, bar.as_ref(), |row| {
+            // The lock is held for a whole record, so that the triples of a
+            // record stay together with `--parallel shared`.
+            let mut output = output.lock().unwrap();
+            output.set_graph(name);
+            let (result, triples, failed) = write_record(
+                output.as_mut(),
+                output_stats.as_mut(),
+                reify.as_ref(),
+                emit_inverse,
+                |counter| convert_record(state, name, writer, counter, row),
+            );
+            drop(output);
+            let warnings = &mut state.warnings;
+            tally.record(warnings, on_error, row.position(), result, triples, failed)
+        })?
+    };
+    let Tally {
+        count,
+        triples,
+        mut skipped,
+        ..
+    } = tally;
+
+    state.output_stats = output_stats;
+    state.warnings.add(Category::MalformedInput, malformed);
+    skipped += malformed;
+    if let Some(bar) = bar {
+        bar.finish_with_message(format!("{} records", count));
+    }
+
+    if let Some(remaining) = &mut state.remaining_records {
+        *remaining -= count as u64;
+    }
     info!("{}: count: {}", name, count);
+    info!("{}: skipped: {}", name, skipped);
     info!("{}: finished", name);
+    state.counts.push(EntityCount {
+        name,
+        records: count as u64,
+        skipped,
+        triples,
+    });
 
     Ok(())
 }
 
-fn write_badge<W: Write>(formatter: &mut NTriplesFormatter<W>, e: &BytesStart) -> Result<()> {
+/// Writes a record to the output through the formatters that add triples of
+/// their own: the statements of `--reify`, the reverse relations of
+/// `--emit-inverse`, and the count of `--emit-void`. Returns the result of
+/// `f` with the number of triples it wrote, and whether the output failed.
+fn write_record(
+    output: &mut dyn Formatter,
+    output_stats: Option<&mut OutputStats>,
+    reify: Option<&Reify>,
+    emit_inverse: bool,
+    f: impl FnOnce(&mut dyn Formatter) -> Result<()>,
+) -> (Result<()>, u64, bool) {
+    let mut counted;
+    let formatter: &mut dyn Formatter = match output_stats {
+        Some(stats) => {
+            counted = OutputCounter::new(output, stats);
+            &mut counted
+        }
+        None => output,
+    };
+    let mut reifier;
+    let formatter: &mut dyn Formatter = match reify {
+        Some(reify) => {
+            reifier = reify.formatter(formatter);
+            &mut reifier
+        }
+        None => formatter,
+    };
+    let mut inverses;
+    let formatter: &mut dyn Formatter = if emit_inverse {
+        inverses = Inverses::new(formatter);
+        &mut inverses
+    } else {
+        formatter
+    };
+    let mut counter = Counter::new(formatter);
+    let result = f(&mut counter);
+    (result, counter.count, counter.failed)
+}
+
+/// Converts a record with the writer of its entity, checking the triples with
+/// `--validate`.
+fn convert_record(
+    state: &mut State,
+    name: &str,
+    writer: Writer,
+    formatter: &mut dyn Formatter,
+    row: &Row,
+) -> Result<()> {
+    if !state.opts.validate {
+        return writer(state, formatter, row);
+    }
+    let mut validator = Validator::new(formatter, state.opts.keep_control_chars);
+    let result = writer(state, &mut validator, row);
+    for problem in validator.problems {
+        state.warnings.warn(
+            Category::InvalidTriple,
+            format_args!(
+                "{}: record at position {}: {}",
+                name,
+                row.position(),
+                problem
+            ),
+        );
+    }
+    result
+}
+
+/// The records of an entity that were written so far.
+struct Tally<'a> {
+    name: &'static str,
+    bar: Option<&'a ProgressBar>,
+    count: usize,
+    triples: u64,
+    skipped: u64,
+}
+
+impl Tally<'_> {
+    /// Counts a record that was written, and handles its error.
+    fn record(
+        &mut self,
+        warnings: &mut Warnings,
+        on_error: OnError,
+        position: u64,
+        result: Result<()>,
+        triples: u64,
+        failed: bool,
+    ) -> Result<()> {
+        match result {
+            // Errors of the output are not the fault of the record, and abort
+            // in any case. The triples that the record wrote before its error
+            // are kept.
+            Err(e) if on_error == OnError::Skip && !failed => {
+                warnings.warn(
+                    Category::MalformedInput,
+                    format_args!(
+                        "{}: record at position {}: {:#}, skipping",
+                        self.name, position, e
+                    ),
+                );
+                self.skipped += 1;
+            }
+            Err(e) if !failed => {
+                return Err(e.context(format!("{}: record at position {}", self.name, position)))
+            }
+            Err(e) => return Err(e),
+            // Records that write nothing were dropped by a filter.
+            Ok(()) if triples == 0 => self.skipped += 1,
+            Ok(()) => {}
+        }
+        self.triples += triples;
+        self.count += 1;
+        if self.count.is_multiple_of(100000) {
+            match self.bar {
+                Some(bar) => set_records(bar, self.count),
+                None => info!("{}: count: {}", self.name, self.count),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn collect_accepted_answers(opts: &Opts) -> Result<HashSet<u64>> {
+    info!("Posts: collecting accepted answers");
+    let mut accepted_answers = HashSet::new();
+
+    for_each_row(opts.read_options(), opts.input_path("Posts"), |row| {
+        for attribute in row.attributes() {
+            let (key, value) = attribute?;
+            if key == b"AcceptedAnswerId" {
+                let accepted_answer_id = value
+                    .parse()
+                    .context("invalid `AcceptedAnswerId` in attributes")?;
+                accepted_answers.insert(accepted_answer_id);
+            }
+        }
+        Ok(())
+    })?;
+
+    info!("Posts: accepted answers: {}", accepted_answers.len());
+    Ok(accepted_answers)
+}
+
+fn collect_post_ids(opts: &Opts, accepted_answers: Option<&HashSet<u64>>) -> Result<HashSet<u64>> {
+    info!("Posts: collecting ids");
+    let mut post_ids = HashSet::new();
+
+    for_each_row(opts.read_options(), opts.input_path("Posts"), |row| {
+        let mut id = None;
+        let mut accepted_answer_id = None;
+        let mut score = None;
+        let mut deletion_date = None;
+        for attribute in row.attributes() {
+            let (key, value) = attribute?;
+            match key {
+                b"Id" => &mut id,
+                b"AcceptedAnswerId" => &mut accepted_answer_id,
+                b"Score" => &mut score,
+                b"DeletionDate" => &mut deletion_date,
+                _ => continue,
+            }
+            .replace(value);
+        }
+
+        let id = id.context("`Id` not found in attributes")?;
+        let id = id.parse().context("invalid `Id` in attributes")?;
+        // Same as the `--posts-only-with-accepted` check in `write_post`.
+        if let Some(accepted_answers) = accepted_answers {
+            if accepted_answer_id.is_none() && !accepted_answers.contains(&id) {
+                return Ok(());
+            }
+        }
+        // Same as the `--skip-deleted` and `--min-post-score` checks in
+        // `write_post`.
+        if opts.skip_deleted && deletion_date.is_some() {
+            return Ok(());
+        }
+        if let Some(min_post_score) = opts.min_post_score {
+            let score = score.as_deref().and_then(|score| score.parse::<i64>().ok());
+            if score.is_some_and(|score| score < min_post_score) {
+                return Ok(());
+            }
+        }
+        post_ids.insert(id);
+        Ok(())
+    })?;
+
+    info!("Posts: ids: {}", post_ids.len());
+    Ok(post_ids)
+}
+
+fn collect_post_owners(opts: &Opts) -> Result<HashMap<u64, i64>> {
+    info!("Posts: collecting owners");
+    let mut post_owners = HashMap::new();
+
+    for_each_row(opts.read_options(), opts.input_path("Posts"), |row| {
+        let mut id = None;
+        let mut owner_user_id = None;
+        for attribute in row.attributes() {
+            let (key, value) = attribute?;
+            match key {
+                b"Id" => &mut id,
+                b"OwnerUserId" => &mut owner_user_id,
+                _ => continue,
+            }
+            .replace(value);
+        }
+
+        if let (Some(id), Some(owner_user_id)) = (id, owner_user_id) {
+            let id = id.parse().context("invalid `Id` in attributes")?;
+            let owner_user_id = owner_user_id
+                .parse()
+                .context("invalid `OwnerUserId` in attributes")?;
+            post_owners.insert(id, owner_user_id);
+        }
+        Ok(())
+    })?;
+
+    info!("Posts: owners: {}", post_owners.len());
+    Ok(post_owners)
+}
+
+fn collect_excluded_users(opts: &Opts, pattern: &Regex) -> Result<HashSet<String>> {
+    info!("Users: collecting excluded users");
+    let mut excluded_users = HashSet::new();
+
+    for_each_row(opts.read_options(), opts.input_path("Users"), |row| {
+        let mut id = None;
+        let mut display_name = None;
+        for attribute in row.attributes() {
+            let (key, value) = attribute?;
+            match key {
+                b"Id" => &mut id,
+                b"DisplayName" => &mut display_name,
+                _ => continue,
+            }
+            .replace(value);
+        }
+
+        if let (Some(id), Some(display_name)) = (id, display_name) {
+            if pattern.is_match(&display_name) {
+                excluded_users.insert(id.into_owned());
+            }
+        }
+        Ok(())
+    })?;
+
+    info!("Users: excluded users: {}", excluded_users.len());
+    Ok(excluded_users)
+}
+
+fn write_badge(state: &mut State, formatter: &mut dyn Formatter, row: &Row) -> Result<()> {
     let mut id = None;
     let mut user_id = None;
     let mut name = None;
@@ -132,49 +2085,83 @@ fn write_badge<W: Write>(formatter: &mut NTriplesFormatter<W>, e: &BytesStart) -
     let mut class = None;
     let mut tag_based = None;
 
-    for attribute in e.attributes() {
-        let attribute = attribute.context("could not parse attribute")?;
-        match attribute.key {
+    let mut unknown = Vec::new();
+
+    for attribute in row.attributes() {
+        let (key, value) = attribute?;
+        match key {
             b"Id" => &mut id,
             b"UserId" => &mut user_id,
             b"Name" => &mut name,
             b"Date" => &mut date,
             b"Class" => &mut class,
             b"TagBased" => &mut tag_based,
-            _ => continue,
+            _ => {
+                if state.unknown_attribute("Badges", key) {
+                    unknown.push((key, value));
+                }
+                continue;
+            }
         }
-        .replace(parse_attribute(attribute)?);
+        .replace(value);
     }
 
-    let mut id = id.context("`Id` not found in attributes")?;
-    id.insert(0, 'b');
+    let id = id.context("`Id` not found in attributes")?;
+    let id = prefixed(state.opts.prefix("badge"), &id);
+    if state.excludes(&id) || state.opts.outside_window(date.as_deref()) {
+        return Ok(());
+    }
 
-    let mut user_id = user_id.context("`UserId` not found in attributes")?;
-    user_id.insert(0, 'u');
-    let triple = id_to_id(&id, "badge.user", &user_id);
-    formatter.format(&triple)?;
+    if state.opts.emit_types {
+        formatter.format(&type_triple(&id, "Badge"))?;
+    }
+
+    let user_id = user_id
+        .or_else(|| state.opts.default_value("badge.user"))
+        .context("`UserId` not found in attributes")?;
+    if !state.opts.is_sentinel("badge.user", &user_id) {
+        let user_id = prefixed(state.opts.prefix("user"), &user_id);
+        let triple = id_to_id(&id, "badge.user", &user_id);
+        formatter.format(&triple)?;
+
+        // `--emit-inverse` writes the same edge.
+        if state.opts.user_badge_edges && !state.opts.emit_inverse {
+            let triple = id_to_id(&user_id, "user.badge", &id);
+            formatter.format(&triple)?;
+        }
+    }
 
-    let name = name.context("`Name` not found in attributes")?;
+    let name = name
+        .or_else(|| state.opts.default_value("badge.name"))
+        .context("`Name` not found in attributes")?;
     let triple = id_to_str(&id, "badge.name", &name);
     formatter.format(&triple)?;
 
-    let date = date.context("`Date` not found in attributes")?;
-    let triple = id_to_str(&id, "badge.date", &date);
+    let date = date
+        .or_else(|| state.opts.default_value("badge.date"))
+        .context("`Date` not found in attributes")?;
+    let triple = id_to_datetime(&mut state.warnings, &id, "badge.date", &date);
     formatter.format(&triple)?;
 
-    let class = class.context("`Class` not found in attributes")?;
-    let triple = id_to_str(&id, "badge.class", &class);
-    formatter.format(&triple)?;
+    let class = class
+        .or_else(|| state.opts.default_value("badge.class"))
+        .context("`Class` not found in attributes")?;
+    if !state.opts.is_sentinel("badge.class", &class) {
+        let triple = id_to_integer(&mut state.warnings, &id, "badge.class", &class);
+        formatter.format(&triple)?;
+    }
 
-    let mut tag_based = tag_based.context("`TagBased` not found in attributes")?;
-    tag_based.make_ascii_lowercase();
-    let triple = id_to_str(&id, "badge.tag_based", &tag_based);
+    let tag_based = tag_based
+        .or_else(|| state.opts.default_value("badge.tag_based"))
+        .context("`TagBased` not found in attributes")?
+        .to_ascii_lowercase();
+    let triple = id_to_boolean(&mut state.warnings, &id, "badge.tag_based", &tag_based);
     formatter.format(&triple)?;
 
-    Ok(())
+    write_unknown(formatter, &id, "badge", &unknown)
 }
 
-fn write_comment<W: Write>(formatter: &mut NTriplesFormatter<W>, e: &BytesStart) -> Result<()> {
+fn write_comment(state: &mut State, formatter: &mut dyn Formatter, row: &Row) -> Result<()> {
     let mut id = None;
     let mut post_id = None;
     let mut score = None;
@@ -184,9 +2171,11 @@ fn write_comment<W: Write>(formatter: &mut NTriplesFormatter<W>, e: &BytesStart)
     let mut user_display_name = None;
     let mut content_license = None;
 
-    for attribute in e.attributes() {
-        let attribute = attribute.context("could not parse attribute")?;
-        match attribute.key {
+    let mut unknown = Vec::new();
+
+    for attribute in row.attributes() {
+        let (key, value) = attribute?;
+        match key {
             b"Id" => &mut id,
             b"PostId" => &mut post_id,
             b"Score" => &mut score,
@@ -195,50 +2184,108 @@ fn write_comment<W: Write>(formatter: &mut NTriplesFormatter<W>, e: &BytesStart)
             b"UserId" => &mut user_id,
             b"UserDisplayName" => &mut user_display_name,
             b"ContentLicense" => &mut content_license,
-            _ => continue,
+            _ => {
+                if state.unknown_attribute("Comments", key) {
+                    unknown.push((key, value));
+                }
+                continue;
+            }
         }
-        .replace(parse_attribute(attribute)?);
+        .replace(value);
     }
 
-    let mut id = id.context("`Id` not found in attributes")?;
-    id.insert(0, 'c');
+    let id = id.context("`Id` not found in attributes")?;
+    let id = prefixed(state.opts.prefix("comment"), &id);
+    if state.excludes(&id) || state.opts.outside_window(creation_date.as_deref()) {
+        return Ok(());
+    }
+    if state.excludes_author(user_id.as_deref()) {
+        return Ok(());
+    }
 
-    let mut post_id = post_id.context("`PostId` not found in attributes")?;
-    post_id.insert(0, 'p');
-    let triple = id_to_id(&id, "comment.post", &post_id);
-    formatter.format(&triple)?;
+    let post_id = post_id
+        .or_else(|| state.opts.default_value("comment.post"))
+        .context("`PostId` not found in attributes")?;
+    if let Some(post_ids) = state.post_ids {
+        let post = post_id.parse().context("invalid `PostId` in attributes")?;
+        if !post_ids.contains(&post) {
+            state.warnings.warn(
+                Category::DanglingReference,
+                format_args!("comment {}: post {} is not emitted, dropping", id, post_id),
+            );
+            return Ok(());
+        }
+    }
+    if state.opts.emit_types {
+        formatter.format(&type_triple(&id, "Comment"))?;
+    }
 
-    let score = score.context("`Score` not found in attributes")?;
-    let triple = id_to_str(&id, "comment.score", &score);
-    formatter.format(&triple)?;
+    if !state.opts.is_sentinel("comment.post", &post_id) {
+        let post_id = prefixed(state.opts.prefix("post"), &post_id);
+        let triple = id_to_id(&id, "comment.post", &post_id);
+        formatter.format(&triple)?;
+    }
+
+    let score = score
+        .or_else(|| state.opts.default_value("comment.score"))
+        .context("`Score` not found in attributes")?;
+    if !state.opts.is_sentinel("comment.score", &score) {
+        let triple = id_to_integer(&mut state.warnings, &id, "comment.score", &score);
+        formatter.format(&triple)?;
+    }
 
-    let text = text.context("`Text` not found in attributes")?;
-    let triple = id_to_str(&id, "comment.text", &text);
+    let text = text
+        .or_else(|| state.opts.default_value("comment.text"))
+        .context("`Text` not found in attributes")?;
+    let text = state.opts.normalize_text(text);
+    let triple = id_to_lang_str(&id, "comment.text", &text, state.opts.lang());
     formatter.format(&triple)?;
 
-    let creation_date = creation_date.context("`CreationDate` not found in attributes")?;
-    let triple = id_to_str(&id, "comment.creation_date", &creation_date);
+    let creation_date = creation_date
+        .or_else(|| state.opts.default_value("comment.creation_date"))
+        .context("`CreationDate` not found in attributes")?;
+    let triple = id_to_datetime(
+        &mut state.warnings,
+        &id,
+        "comment.creation_date",
+        &creation_date,
+    );
     formatter.format(&triple)?;
+    if state.opts.emit_year {
+        if let Some(triple) = id_to_year(&id, "comment.creation_year", &creation_date) {
+            formatter.format(&triple)?;
+        }
+    }
 
-    if let Some(mut user_id) = user_id {
-        user_id.insert(0, 'u');
+    let user_id = user_id.filter(|v| !state.opts.is_sentinel("comment.user", v));
+    if let Some(user_id) = &user_id {
+        let user_id = prefixed(state.opts.prefix("user"), user_id);
         let triple = id_to_id(&id, "comment.user", &user_id);
         formatter.format(&triple)?;
     }
 
-    if let Some(user_display_name) = user_display_name {
+    if let Some(user_display_name) = user_display_name.filter(|_| !state.opts.anonymize) {
         let triple = id_to_str(&id, "comment.user_display_name", &user_display_name);
         formatter.format(&triple)?;
+
+        if state.opts.anonymous_owners && user_id.is_none() {
+            let name = &user_display_name;
+            write_anonymous_user(state.opts, formatter, &id, "comment.user", name)?;
+        }
     }
 
-    let content_license = content_license.context("`ContentLicense` not found in attributes")?;
-    let triple = id_to_str(&id, "comment.content_license", &content_license);
-    formatter.format(&triple)?;
+    // Comments from dumps before 2018 predate `ContentLicense`.
+    let content_license =
+        content_license.or_else(|| state.opts.default_value("comment.content_license"));
+    if let Some(content_license) = content_license {
+        let triple = id_to_str(&id, "comment.content_license", &content_license);
+        formatter.format(&triple)?;
+    }
 
-    Ok(())
+    write_unknown(formatter, &id, "comment", &unknown)
 }
 
-fn write_post<W: Write>(formatter: &mut NTriplesFormatter<W>, e: &BytesStart) -> Result<()> {
+fn write_post(state: &mut State, formatter: &mut dyn Formatter, row: &Row) -> Result<()> {
     let mut id = None;
     let mut type_ = None;
     let mut accepted_answer_id = None;
@@ -263,9 +2310,11 @@ fn write_post<W: Write>(formatter: &mut NTriplesFormatter<W>, e: &BytesStart) ->
     let mut community_owned_date = None;
     let mut content_license = None;
 
-    for attribute in e.attributes() {
-        let attribute = attribute.context("could not parse attribute")?;
-        match attribute.key {
+    let mut unknown = Vec::new();
+
+    for attribute in row.attributes() {
+        let (key, value) = attribute?;
+        match key {
             b"Id" => &mut id,
             b"PostTypeId" => &mut type_,
             b"AcceptedAnswerId" => &mut accepted_answer_id,
@@ -289,137 +2338,321 @@ fn write_post<W: Write>(formatter: &mut NTriplesFormatter<W>, e: &BytesStart) ->
             b"ClosedDate" => &mut closed_date,
             b"CommunityOwnedDate" => &mut community_owned_date,
             b"ContentLicense" => &mut content_license,
-            _ => continue,
+            _ => {
+                if state.unknown_attribute("Posts", key) {
+                    unknown.push((key, value));
+                }
+                continue;
+            }
+        }
+        .replace(value);
+    }
+
+    let id = id.context("`Id` not found in attributes")?;
+    if let Some(accepted_answers) = &state.accepted_answers {
+        let post_id = id.parse().context("invalid `Id` in attributes")?;
+        if accepted_answer_id.is_none() && !accepted_answers.contains(&post_id) {
+            return Ok(());
+        }
+    }
+    let id = prefixed(state.opts.prefix("post"), &id);
+    if state.excludes(&id) || state.opts.outside_window(creation_date.as_deref()) {
+        return Ok(());
+    }
+    if state.opts.skip_deleted && deletion_date.is_some() {
+        return Ok(());
+    }
+    if let Some(min_post_score) = state.opts.min_post_score {
+        // An unparseable score is warned about when it is emitted below.
+        let score = score.as_deref().and_then(|score| score.parse::<i64>().ok());
+        if score.is_some_and(|score| score < min_post_score) {
+            return Ok(());
+        }
+    }
+    if state.excludes_author(owner_id.as_deref()) {
+        return Ok(());
+    }
+
+    let opts = state.opts;
+    let tags = match tags.as_deref() {
+        Some(value) => {
+            let tags = split_tags(value);
+            if tags.is_none() {
+                state.warnings.warn(
+                    Category::InvalidTags,
+                    format_args!("{}: invalid `post.tags` {:?}, writing no tags", id, value),
+                );
+            }
+            tags
         }
-        .replace(parse_attribute(attribute)?);
-    }
+        None => None,
+    };
+
+    let mut partitioned;
+    let formatter: &mut dyn Formatter = match (&mut state.posts_by_tag, &tags) {
+        (Some(posts_by_tag), Some(tags)) => {
+            partitioned = Tee {
+                primary: formatter,
+                secondary: posts_by_tag.formatters(tags)?,
+            };
+            &mut partitioned
+        }
+        _ => formatter,
+    };
 
-    let mut id = id.context("`Id` not found in attributes")?;
-    id.insert(0, 'p');
+    if opts.emit_types {
+        formatter.format(&type_triple(&id, "Post"))?;
+    }
 
-    let type_ = type_.context("`PostTypeId` not found in attributes")?;
-    let triple = id_to_str(&id, "post.type", &type_);
-    formatter.format(&triple)?;
+    let type_ = type_
+        .or_else(|| opts.default_value("post.type"))
+        .context("`PostTypeId` not found in attributes")?;
+    if !opts.is_sentinel("post.type", &type_) {
+        if opts.type_names {
+            let warnings = &mut state.warnings;
+            write_type_name(warnings, formatter, &id, "PostTypeId", POST_TYPES, &type_)?;
+        }
+        let triple = id_to_integer(&mut state.warnings, &id, "post.type", &type_);
+        formatter.format(&triple)?;
+    }
 
-    if let Some(mut accepted_answer_id) = accepted_answer_id {
-        accepted_answer_id.insert(0, 'p');
+    if let Some(accepted_answer_id) =
+        accepted_answer_id.filter(|v| !opts.is_sentinel("post.accepted_answer", v))
+    {
+        let accepted_answer_id = prefixed(state.opts.prefix("post"), &accepted_answer_id);
         let triple = id_to_id(&id, "post.accepted_answer", &accepted_answer_id);
         formatter.format(&triple)?;
     }
 
-    if let Some(mut parent_id) = parent_id {
-        parent_id.insert(0, 'p');
+    if let Some(parent_id) = parent_id.filter(|v| !opts.is_sentinel("post.parent", v)) {
+        let parent_id = prefixed(state.opts.prefix("post"), &parent_id);
         let triple = id_to_id(&id, "post.parent", &parent_id);
         formatter.format(&triple)?;
     }
 
-    let creation_date = creation_date.context("`CreationDate` not found in attributes")?;
-    let triple = id_to_str(&id, "post.creation_date", &creation_date);
+    if let Some(answer_ranks) = state.answer_ranks {
+        let post_id = id[opts.prefix("post").len()..]
+            .parse()
+            .context("invalid `Id` in attributes")?;
+        if let Some(rank) = answer_ranks.rank(post_id) {
+            let rank = rank.to_string();
+            let triple = id_to_typed(&id, "post.answer_rank", &rank, XSD_INTEGER);
+            formatter.format(&triple)?;
+
+            let is_accepted = answer_ranks.is_accepted(post_id).to_string();
+            let triple = id_to_typed(&id, "post.is_accepted", &is_accepted, XSD_BOOLEAN);
+            formatter.format(&triple)?;
+        }
+    }
+
+    let creation_date = creation_date
+        .or_else(|| opts.default_value("post.creation_date"))
+        .context("`CreationDate` not found in attributes")?;
+    let triple = id_to_datetime(
+        &mut state.warnings,
+        &id,
+        "post.creation_date",
+        &creation_date,
+    );
     formatter.format(&triple)?;
+    if opts.emit_year {
+        if let Some(triple) = id_to_year(&id, "post.creation_year", &creation_date) {
+            formatter.format(&triple)?;
+        }
+    }
 
     if let Some(deletion_date) = deletion_date {
-        let triple = id_to_str(&id, "post.deletion_date", &deletion_date);
+        let triple = id_to_datetime(
+            &mut state.warnings,
+            &id,
+            "post.deletion_date",
+            &deletion_date,
+        );
         formatter.format(&triple)?;
     }
 
-    let score = score.context("`Score` not found in attributes")?;
-    let triple = id_to_str(&id, "post.score", &score);
-    formatter.format(&triple)?;
+    let score = score
+        .or_else(|| opts.default_value("post.score"))
+        .context("`Score` not found in attributes")?;
+    if !opts.is_sentinel("post.score", &score) {
+        let triple = id_to_integer(&mut state.warnings, &id, "post.score", &score);
+        formatter.format(&triple)?;
+    }
 
-    if let Some(view_count) = view_count {
-        let triple = id_to_str(&id, "post.view_count", &view_count);
+    if let Some(view_count) = view_count.filter(|v| !opts.is_sentinel("post.view_count", v)) {
+        let triple = id_to_integer(&mut state.warnings, &id, "post.view_count", &view_count);
         formatter.format(&triple)?;
+
+        if opts.view_buckets {
+            match view_count.parse() {
+                Ok(view_count) => {
+                    let view_bucket = view_bucket(view_count, &opts.view_bucket_thresholds);
+                    let triple = id_to_str(&id, "post.view_bucket", &view_bucket);
+                    formatter.format(&triple)?;
+                }
+                Err(_) => state.warnings.warn(
+                    Category::InvalidInteger,
+                    format_args!(
+                        "{}: invalid `post.view_count` {:?}, writing no `post.view_bucket`",
+                        id, view_count
+                    ),
+                ),
+            }
+        }
     }
 
-    let body = body.context("`Body` not found in attributes")?;
-    let triple = id_to_str(&id, "post.body", &body);
+    let body = body
+        .or_else(|| opts.default_value("post.body"))
+        .context("`Body` not found in attributes")?;
+    let body = opts.normalize_text(body);
+    let triple = id_to_lang_str(&id, "post.body", &body, opts.lang());
     formatter.format(&triple)?;
 
-    if let Some(mut owner_id) = owner_id {
-        owner_id.insert(0, 'u');
+    let owner_id = owner_id.filter(|v| !opts.is_sentinel("post.owner", v));
+    if let Some(owner_id) = &owner_id {
+        let owner_id = prefixed(state.opts.prefix("user"), owner_id);
         let triple = id_to_id(&id, "post.owner", &owner_id);
         formatter.format(&triple)?;
     }
 
-    if let Some(owner_display_name) = owner_display_name {
+    if let Some(owner_display_name) = owner_display_name.filter(|_| !opts.anonymize) {
         let triple = id_to_str(&id, "post.owner_display_name", &owner_display_name);
         formatter.format(&triple)?;
+
+        if opts.anonymous_owners && owner_id.is_none() {
+            write_anonymous_user(opts, formatter, &id, "post.owner", &owner_display_name)?;
+        }
     }
 
-    if let Some(mut last_editor_id) = last_editor_id {
-        last_editor_id.insert(0, 'u');
+    let last_editor_id = last_editor_id.filter(|v| !opts.is_sentinel("post.last_editor", v));
+    if let Some(last_editor_id) = &last_editor_id {
+        let last_editor_id = prefixed(state.opts.prefix("user"), last_editor_id);
         let triple = id_to_id(&id, "post.last_editor", &last_editor_id);
         formatter.format(&triple)?;
     }
 
-    if let Some(last_editor_display_name) = last_editor_display_name {
+    if let Some(last_editor_display_name) = last_editor_display_name.filter(|_| !opts.anonymize) {
         let triple = id_to_str(
             &id,
             "post.last_editor_display_name",
             &last_editor_display_name,
         );
         formatter.format(&triple)?;
+
+        if opts.anonymous_owners && last_editor_id.is_none() {
+            let name = &last_editor_display_name;
+            write_anonymous_user(opts, formatter, &id, "post.last_editor", name)?;
+        }
     }
 
     if let Some(last_edit_date) = last_edit_date {
-        let triple = id_to_str(&id, "post.last_edit_date", &last_edit_date);
+        let triple = id_to_datetime(
+            &mut state.warnings,
+            &id,
+            "post.last_edit_date",
+            &last_edit_date,
+        );
         formatter.format(&triple)?;
     }
 
     if let Some(last_activity_date) = last_activity_date {
-        let triple = id_to_str(&id, "post.last_activity_date", &last_activity_date);
+        let triple = id_to_datetime(
+            &mut state.warnings,
+            &id,
+            "post.last_activity_date",
+            &last_activity_date,
+        );
+        formatter.format(&triple)?;
+    }
+
+    if let Some(title) = &title {
+        let triple = id_to_lang_str(&id, "post.title", title, opts.lang());
         formatter.format(&triple)?;
     }
 
-    if let Some(title) = title {
-        let triple = id_to_str(&id, "post.title", &title);
+    if let Some(algorithm) = opts.content_hash {
+        let content_hash = content_hash(algorithm, title.as_deref().unwrap_or(""), &body);
+        let triple = id_to_str(&id, "post.content_hash", &content_hash);
         formatter.format(&triple)?;
     }
 
-    if let Some(mut tags) = tags {
-        tags.remove(0);
-        tags.pop();
-        for tag in tags.split("><") {
-            let mut tag_id = BASE32_NOPAD.encode(tag.as_bytes());
-            tag_id.insert(0, 't');
+    if let Some(tags) = &tags {
+        for tag in tags {
+            let tag_id = state.opts.tag_id(tag);
             let triple = id_to_id(&id, "post.tags", &tag_id);
             formatter.format(&triple)?;
         }
+        if opts.inline_tags {
+            for tag in tags {
+                let triple = id_to_str(&id, "post.tag_name", tag);
+                formatter.format(&triple)?;
+            }
+        }
+        if let Some(tag_cooccurrence) = &mut state.tag_cooccurrence {
+            tag_cooccurrence.add(tags, &mut state.warnings);
+        }
     }
 
+    let answer_count = answer_count.filter(|v| !opts.is_sentinel("post.answer_count", v));
     if let Some(answer_count) = answer_count {
-        let triple = id_to_str(&id, "post.answer_count", &answer_count);
+        let triple = id_to_integer(&mut state.warnings, &id, "post.answer_count", &answer_count);
         formatter.format(&triple)?;
     }
 
-    if let Some(comment_count) = comment_count {
-        let triple = id_to_str(&id, "post.comment_count", &comment_count);
+    if let Some(comment_count) =
+        comment_count.filter(|v| !opts.is_sentinel("post.comment_count", v))
+    {
+        let triple = id_to_integer(
+            &mut state.warnings,
+            &id,
+            "post.comment_count",
+            &comment_count,
+        );
         formatter.format(&triple)?;
     }
 
-    if let Some(favorite_count) = favorite_count {
-        let triple = id_to_str(&id, "post.favorite_count", &favorite_count);
+    if let Some(favorite_count) =
+        favorite_count.filter(|v| !opts.is_sentinel("post.favorite_count", v))
+    {
+        let triple = id_to_integer(
+            &mut state.warnings,
+            &id,
+            "post.favorite_count",
+            &favorite_count,
+        );
         formatter.format(&triple)?;
     }
 
     if let Some(closed_date) = closed_date {
-        let triple = id_to_str(&id, "post.closed_date", &closed_date);
+        let triple = id_to_datetime(&mut state.warnings, &id, "post.closed_date", &closed_date);
         formatter.format(&triple)?;
     }
 
     if let Some(community_owned_date) = community_owned_date {
-        let triple = id_to_str(&id, "post.community_owned_date", &community_owned_date);
+        let triple = id_to_datetime(
+            &mut state.warnings,
+            &id,
+            "post.community_owned_date",
+            &community_owned_date,
+        );
         formatter.format(&triple)?;
     }
 
-    let content_license = content_license.context("`ContentLicense` not found in attributes")?;
+    let content_license = content_license
+        .or_else(|| opts.default_value("post.content_license"))
+        .context("`ContentLicense` not found in attributes")?;
     let triple = id_to_str(&id, "post.content_license", &content_license);
     formatter.format(&triple)?;
 
-    Ok(())
+    write_unknown(formatter, &id, "post", &unknown)
 }
 
-fn write_posthistory<W: Write>(formatter: &mut NTriplesFormatter<W>, e: &BytesStart) -> Result<()> {
+/// `PostHistoryTypeId`s of edits to the title, body and tags of a post. The
+/// initial revisions (1-3) are authorship rather than edits, and rollbacks
+/// (7-9) only restore an earlier edit.
+const EDIT_TYPES: &[&str] = &["4", "5", "6"];
+
+fn write_posthistory(state: &mut State, formatter: &mut dyn Formatter, row: &Row) -> Result<()> {
     let mut id = None;
     let mut type_ = None;
     let mut post_id = None;
@@ -431,9 +2664,11 @@ fn write_posthistory<W: Write>(formatter: &mut NTriplesFormatter<W>, e: &BytesSt
     let mut text = None;
     let mut content_license = None;
 
-    for attribute in e.attributes() {
-        let attribute = attribute.context("could not parse attribute")?;
-        match attribute.key {
+    let mut unknown = Vec::new();
+
+    for attribute in row.attributes() {
+        let (key, value) = attribute?;
+        match key {
             b"Id" => &mut id,
             b"PostHistoryTypeId" => &mut type_,
             b"PostId" => &mut post_id,
@@ -444,40 +2679,113 @@ fn write_posthistory<W: Write>(formatter: &mut NTriplesFormatter<W>, e: &BytesSt
             b"Comment" => &mut comment,
             b"Text" => &mut text,
             b"ContentLicense" => &mut content_license,
-            _ => continue,
+            _ => {
+                if state.unknown_attribute("PostHistory", key) {
+                    unknown.push((key, value));
+                }
+                continue;
+            }
         }
-        .replace(parse_attribute(attribute)?);
+        .replace(value);
     }
 
-    let mut id = id.context("`Id` not found in attributes")?;
-    id.insert(0, 'h');
+    let id = id.context("`Id` not found in attributes")?;
+    let id = prefixed(state.opts.prefix("posthistory"), &id);
+    if state.excludes(&id) || state.opts.outside_window(creation_date.as_deref()) {
+        return Ok(());
+    }
 
-    let type_ = type_.context("`PostHistoryTypeId` not found in attributes")?;
-    let triple = id_to_str(&id, "posthistory.type", &type_);
-    formatter.format(&triple)?;
+    if state.opts.emit_types {
+        formatter.format(&type_triple(&id, "PostHistory"))?;
+    }
 
-    let mut post_id = post_id.context("`PostHistoryTypeId` not found in attributes")?;
-    post_id.insert(0, 'p');
-    let triple = id_to_id(&id, "posthistory.post", &post_id);
-    formatter.format(&triple)?;
+    let type_ = type_
+        .or_else(|| state.opts.default_value("posthistory.type"))
+        .context("`PostHistoryTypeId` not found in attributes")?;
+    if !state.opts.is_sentinel("posthistory.type", &type_) {
+        if state.opts.type_names {
+            let (warnings, types) = (&mut state.warnings, POST_HISTORY_TYPES);
+            write_type_name(warnings, formatter, &id, "PostHistoryTypeId", types, &type_)?;
+        }
+        let triple = id_to_integer(&mut state.warnings, &id, "posthistory.type", &type_);
+        formatter.format(&triple)?;
+    }
+
+    let post_id = post_id
+        .or_else(|| state.opts.default_value("posthistory.post"))
+        .context("`PostId` not found in attributes")?;
+
+    // An edit of someone else's post, for `--editor-edges`. Without a known
+    // owner, e.g. for a post that is not in Posts.xml or has no `OwnerUserId`,
+    // an edit cannot be told apart from the owner's own.
+    let edited_post = match (state.post_owners, &user_id) {
+        (Some(post_owners), Some(user_id)) if EDIT_TYPES.contains(&&*type_) => {
+            let owner = post_id
+                .parse()
+                .ok()
+                .and_then(|post_id| post_owners.get(&post_id));
+            match owner {
+                Some(&owner) if Some(owner) != user_id.parse().ok() => {
+                    Some(prefixed(state.opts.prefix("post"), &post_id).to_string())
+                }
+                Some(_) => None,
+                None => {
+                    state.warnings.warn(
+                        Category::DanglingReference,
+                        format_args!(
+                            "{}: owner of post {} is unknown, writing no `user.edited`",
+                            id, post_id
+                        ),
+                    );
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+    if !state.opts.is_sentinel("posthistory.post", &post_id) {
+        let post_id = prefixed(state.opts.prefix("post"), &post_id);
+        let triple = id_to_id(&id, "posthistory.post", &post_id);
+        formatter.format(&triple)?;
+    }
 
-    let revision_guid = revision_guid.context("`RevisionGUID` not found in attributes")?;
+    let revision_guid = revision_guid
+        .or_else(|| state.opts.default_value("posthistory.revision_guid"))
+        .context("`RevisionGUID` not found in attributes")?;
     let triple = id_to_str(&id, "posthistory.revision_guid", &revision_guid);
     formatter.format(&triple)?;
 
-    let creation_date = creation_date.context("`CreationDate` not found in attributes")?;
-    let triple = id_to_str(&id, "posthistory.creation_date", &creation_date);
+    let creation_date = creation_date
+        .or_else(|| state.opts.default_value("posthistory.creation_date"))
+        .context("`CreationDate` not found in attributes")?;
+    let triple = id_to_datetime(
+        &mut state.warnings,
+        &id,
+        "posthistory.creation_date",
+        &creation_date,
+    );
     formatter.format(&triple)?;
 
-    if let Some(mut user_id) = user_id {
-        user_id.insert(0, 'u');
+    let user_id = user_id.filter(|v| !state.opts.is_sentinel("posthistory.user", v));
+    if let Some(user_id) = &user_id {
+        let user_id = prefixed(state.opts.prefix("user"), user_id);
         let triple = id_to_id(&id, "posthistory.user", &user_id);
         formatter.format(&triple)?;
+
+        if let Some(edited_post) = &edited_post {
+            let triple = id_to_id(&user_id, "user.edited", edited_post);
+            formatter.format(&triple)?;
+        }
     }
 
-    if let Some(user_display_name) = user_display_name {
+    if let Some(user_display_name) = user_display_name.filter(|_| !state.opts.anonymize) {
         let triple = id_to_str(&id, "posthistory.user_display_name", &user_display_name);
         formatter.format(&triple)?;
+
+        if state.opts.anonymous_owners && user_id.is_none() {
+            let name = &user_display_name;
+            write_anonymous_user(state.opts, formatter, &id, "posthistory.user", name)?;
+        }
     }
 
     if let Some(comment) = comment {
@@ -486,7 +2794,8 @@ fn write_posthistory<W: Write>(formatter: &mut NTriplesFormatter<W>, e: &BytesSt
     }
 
     if let Some(text) = text {
-        let triple = id_to_str(&id, "posthistory.text", &text);
+        let text = state.opts.normalize_text(text);
+        let triple = id_to_lang_str(&id, "posthistory.text", &text, state.opts.lang());
         formatter.format(&triple)?;
     }
 
@@ -495,91 +2804,254 @@ fn write_posthistory<W: Write>(formatter: &mut NTriplesFormatter<W>, e: &BytesSt
         formatter.format(&triple)?;
     }
 
-    Ok(())
+    write_unknown(formatter, &id, "posthistory", &unknown)
 }
 
-fn write_postlink<W: Write>(formatter: &mut NTriplesFormatter<W>, e: &BytesStart) -> Result<()> {
+fn write_postlink(state: &mut State, formatter: &mut dyn Formatter, row: &Row) -> Result<()> {
     let mut id = None;
     let mut creation_date = None;
     let mut post_id = None;
     let mut related_post_id = None;
     let mut link_type = None;
 
-    for attribute in e.attributes() {
-        let attribute = attribute.context("could not parse attribute")?;
-        match attribute.key {
+    let mut unknown = Vec::new();
+
+    for attribute in row.attributes() {
+        let (key, value) = attribute?;
+        match key {
             b"Id" => &mut id,
             b"CreationDate" => &mut creation_date,
             b"PostId" => &mut post_id,
             b"RelatedPostId" => &mut related_post_id,
             b"LinkTypeId" => &mut link_type,
-            _ => continue,
+            _ => {
+                if state.unknown_attribute("PostLinks", key) {
+                    unknown.push((key, value));
+                }
+                continue;
+            }
         }
-        .replace(parse_attribute(attribute)?);
+        .replace(value);
     }
 
-    let mut id = id.context("`Id` not found in attributes")?;
-    id.insert(0, 'l');
+    let id = id.context("`Id` not found in attributes")?;
+    let id = prefixed(state.opts.prefix("postlink"), &id);
+    if state.excludes(&id) || state.opts.outside_window(creation_date.as_deref()) {
+        return Ok(());
+    }
 
-    let creation_date = creation_date.context("`CreationDate` not found in attributes")?;
-    let triple = id_to_str(&id, "postlink.creation_date", &creation_date);
-    formatter.format(&triple)?;
+    if state.opts.emit_types {
+        formatter.format(&type_triple(&id, "PostLink"))?;
+    }
 
-    let mut post_id = post_id.context("`PostId` not found in attributes")?;
-    post_id.insert(0, 'p');
-    let triple = id_to_id(&id, "postlink.post", &post_id);
+    let creation_date = creation_date
+        .or_else(|| state.opts.default_value("postlink.creation_date"))
+        .context("`CreationDate` not found in attributes")?;
+    let triple = id_to_datetime(
+        &mut state.warnings,
+        &id,
+        "postlink.creation_date",
+        &creation_date,
+    );
     formatter.format(&triple)?;
 
-    let mut related_post_id = related_post_id.context("`RelatedPostId` not found in attributes")?;
-    related_post_id.insert(0, 'p');
-    let triple = id_to_id(&id, "postlink.related_post", &related_post_id);
-    formatter.format(&triple)?;
+    let post_id = post_id
+        .or_else(|| state.opts.default_value("postlink.post"))
+        .context("`PostId` not found in attributes")?;
+    if !state.opts.is_sentinel("postlink.post", &post_id) {
+        let post_id = prefixed(state.opts.prefix("post"), &post_id);
+        let triple = id_to_id(&id, "postlink.post", &post_id);
+        formatter.format(&triple)?;
+    }
 
-    let link_type = link_type.context("`LinkType` not found in attributes")?;
-    let triple = id_to_str(&id, "postlink.link_type", &link_type);
-    formatter.format(&triple)?;
+    let related_post_id = related_post_id
+        .or_else(|| state.opts.default_value("postlink.related_post"))
+        .context("`RelatedPostId` not found in attributes")?;
+    if !state
+        .opts
+        .is_sentinel("postlink.related_post", &related_post_id)
+    {
+        let related_post_id = prefixed(state.opts.prefix("post"), &related_post_id);
+        let triple = id_to_id(&id, "postlink.related_post", &related_post_id);
+        formatter.format(&triple)?;
+    }
 
-    Ok(())
+    let link_type = link_type
+        .or_else(|| state.opts.default_value("postlink.link_type"))
+        .context("`LinkTypeId` not found in attributes")?;
+    if !state.opts.is_sentinel("postlink.link_type", &link_type) {
+        match type_name(LINK_TYPES, &link_type) {
+            Some(name) if state.opts.link_type_names => {
+                let triple = id_to_iri(&id, "postlink.link_type", name);
+                formatter.format(&triple)?;
+
+                let triple = id_to_integer(
+                    &mut state.warnings,
+                    &id,
+                    "postlink.link_type_id",
+                    &link_type,
+                );
+                formatter.format(&triple)?;
+            }
+            _ => {
+                if state.opts.link_type_names {
+                    state.warnings.warn(
+                        Category::UnknownType,
+                        format_args!(
+                            "{}: unknown `LinkTypeId` {:?}, writing it as is",
+                            id, link_type
+                        ),
+                    );
+                }
+                let triple =
+                    id_to_integer(&mut state.warnings, &id, "postlink.link_type", &link_type);
+                formatter.format(&triple)?;
+            }
+        }
+    }
+
+    write_unknown(formatter, &id, "postlink", &unknown)
+}
+
+/// Links a record that only has the display name of a deleted user to a
+/// placeholder user with that name, for `--anonymous-owners`. The placeholder
+/// is named by a hash of the display name, so all records with the same name
+/// share it.
+fn write_anonymous_user(
+    opts: &Opts,
+    formatter: &mut dyn Formatter,
+    id: &str,
+    iri: &str,
+    display_name: &str,
+) -> Result<()> {
+    let user_id = anonymous_user_id(opts.prefix("user"), display_name);
+    formatter.format(&id_to_id(id, iri, &user_id))?;
+    if opts.emit_types {
+        formatter.format(&type_triple(&user_id, "User"))?;
+    }
+    formatter.format(&id_to_str(&user_id, "user.display_name", display_name))
+}
+
+/// Writes an `rdf:type` triple to the class of a type code for
+/// `--type-names`, or warns if the code is unknown.
+fn write_type_name(
+    warnings: &mut Warnings,
+    formatter: &mut dyn Formatter,
+    id: &str,
+    attr: &str,
+    types: &'static [(&str, &str)],
+    type_: &str,
+) -> Result<()> {
+    match type_name(types, type_) {
+        Some(name) => formatter.format(&type_triple(id, name)),
+        None => {
+            warnings.warn(
+                Category::UnknownType,
+                format_args!("{}: unknown `{}` {:?}, writing no type", id, attr, type_),
+            );
+            Ok(())
+        }
+    }
 }
 
-fn write_tag<W: Write>(formatter: &mut NTriplesFormatter<W>, e: &BytesStart) -> Result<()> {
+fn write_tag(state: &mut State, formatter: &mut dyn Formatter, row: &Row) -> Result<()> {
     let mut name = None;
     let mut count = None;
     let mut excerpt_post_id = None;
     let mut wiki_post_id = None;
 
-    for attribute in e.attributes() {
-        let attribute = attribute.context("could not parse attribute")?;
-        match attribute.key {
+    let mut unknown = Vec::new();
+
+    for attribute in row.attributes() {
+        let (key, value) = attribute?;
+        match key {
             b"TagName" => &mut name,
             b"Count" => &mut count,
             b"ExcerptPostId" => &mut excerpt_post_id,
             b"WikiPostId" => &mut wiki_post_id,
-            _ => continue,
+            // Tags are identified by their name instead.
+            b"Id" => continue,
+            _ => {
+                if state.unknown_attribute("Tags", key) {
+                    unknown.push((key, value));
+                }
+                continue;
+            }
         }
-        .replace(parse_attribute(attribute)?);
+        .replace(value);
     }
 
-    let name = name.context("`Name` not found in attributes")?;
-    let mut id = BASE32_NOPAD.encode(name.as_bytes());
-    id.insert(0, 't');
+    let name = name.context("`TagName` not found in attributes")?;
+    let id = state.opts.tag_id(&name);
+    if state.excludes(&id) {
+        return Ok(());
+    }
+    if state.opts.emit_types {
+        formatter.format(&type_triple(&id, "Tag"))?;
+    }
     let triple = id_to_str(&id, "tag.name", &name);
     formatter.format(&triple)?;
 
-    let count = count.context("`Count` not found in attributes")?;
-    let triple = id_to_str(&id, "tag.count", &count);
-    formatter.format(&triple)?;
+    let count = count
+        .or_else(|| state.opts.default_value("tag.count"))
+        .context("`Count` not found in attributes")?;
+    if !state.opts.is_sentinel("tag.count", &count) {
+        let triple = id_to_integer(&mut state.warnings, &id, "tag.count", &count);
+        formatter.format(&triple)?;
+    }
+
+    if let Some(excerpt_post_id) =
+        excerpt_post_id.filter(|v| !state.opts.is_sentinel("tag.excerpt_post", v))
+    {
+        let excerpt_post_id = prefixed(state.opts.prefix("post"), &excerpt_post_id);
+        let triple = id_to_id(&id, "tag.excerpt_post", &excerpt_post_id);
+        formatter.format(&triple)?;
+    }
 
-    if let Some(mut wiki_post_id) = wiki_post_id {
-        wiki_post_id.insert(0, 'p');
+    if let Some(wiki_post_id) = wiki_post_id.filter(|v| !state.opts.is_sentinel("tag.wiki_post", v))
+    {
+        let wiki_post_id = prefixed(state.opts.prefix("post"), &wiki_post_id);
         let triple = id_to_id(&id, "tag.wiki_post", &wiki_post_id);
         formatter.format(&triple)?;
     }
 
+    write_unknown(formatter, &id, "tag", &unknown)
+}
+
+fn write_tag_cooccurrence(
+    opts: &Opts,
+    formatter: &mut dyn Formatter,
+    tag_cooccurrence: TagCooccurrence,
+) -> Result<()> {
+    info!("TagCooccurrence: started");
+
+    let pairs = tag_cooccurrence.into_pairs();
+    for (a, b, weight) in &pairs {
+        let (a, b) = (opts.tag_id(a), opts.tag_id(b));
+        let triple = id_to_id(&a, "tag.cooccurs_with", &b);
+        formatter.format(&triple)?;
+
+        let tag = opts.prefix("tag").len();
+        let id = format!("{}{}_{}", opts.prefix("cooccurrence"), &a[tag..], &b[tag..]);
+        if opts.emit_types {
+            formatter.format(&type_triple(&id, "TagCooccurrence"))?;
+        }
+        let triple = id_to_id(&id, "cooccurrence.tag", &a);
+        formatter.format(&triple)?;
+        let triple = id_to_id(&id, "cooccurrence.tag", &b);
+        formatter.format(&triple)?;
+        let weight = weight.to_string();
+        let triple = id_to_typed(&id, "cooccurrence.weight", &weight, XSD_INTEGER);
+        formatter.format(&triple)?;
+    }
+
+    info!("TagCooccurrence: pairs: {}", pairs.len());
+    info!("TagCooccurrence: finished");
+
     Ok(())
 }
 
-fn write_user<W: Write>(formatter: &mut NTriplesFormatter<W>, e: &BytesStart) -> Result<()> {
+fn write_user(state: &mut State, formatter: &mut dyn Formatter, row: &Row) -> Result<()> {
     let mut id = None;
     let mut reputation = None;
     let mut creation_date = None;
@@ -594,9 +3066,16 @@ fn write_user<W: Write>(formatter: &mut NTriplesFormatter<W>, e: &BytesStart) ->
     let mut profile_image_url = None;
     let mut account_id = None;
 
-    for attribute in e.attributes() {
-        let attribute = attribute.context("could not parse attribute")?;
-        match attribute.key {
+    let mut unknown = Vec::new();
+
+    for attribute in row.attributes() {
+        let (key, value) = attribute?;
+        match key {
+            b"WebsiteUrl" | b"Location" | b"AboutMe" | b"ProfileImageUrl"
+                if state.opts.anonymize =>
+            {
+                continue
+            }
             b"Id" => &mut id,
             b"Reputation" => &mut reputation,
             b"CreationDate" => &mut creation_date,
@@ -610,32 +3089,102 @@ fn write_user<W: Write>(formatter: &mut NTriplesFormatter<W>, e: &BytesStart) ->
             b"DownVotes" => &mut downvotes,
             b"ProfileImageUrl" => &mut profile_image_url,
             b"AccountId" => &mut account_id,
-            _ => continue,
+            _ => {
+                if state.unknown_attribute("Users", key) {
+                    unknown.push((key, value));
+                }
+                continue;
+            }
+        }
+        .replace(value);
+    }
+
+    let id = id.context("`Id` not found in attributes")?;
+    let id = prefixed(state.opts.prefix("user"), &id);
+    if state.excludes(&id) || state.opts.outside_window(creation_date.as_deref()) {
+        return Ok(());
+    }
+    if let (Some(pattern), Some(display_name)) = (&state.opts.exclude_users_matching, &display_name)
+    {
+        if pattern.is_match(display_name) {
+            return Ok(());
         }
-        .replace(parse_attribute(attribute)?);
     }
 
-    let mut id = id.context("`Id` not found in attributes")?;
-    id.insert(0, 'u');
+    let reputation = reputation
+        .or_else(|| state.opts.default_value("user.reputation"))
+        .context("`Reputation` not found in attributes")?;
+    if let Some(min_reputation) = state.opts.min_reputation {
+        match reputation.parse::<i64>() {
+            Ok(reputation) if reputation >= min_reputation => {}
+            Ok(_) => return Ok(()),
+            Err(_) => {
+                state.warnings.warn(
+                    Category::InvalidInteger,
+                    format_args!(
+                        "{}: invalid `user.reputation` {:?}, dropping the user for \
+                         --min-reputation",
+                        id, reputation
+                    ),
+                );
+                return Ok(());
+            }
+        }
+    }
+    if state.opts.emit_types {
+        formatter.format(&type_triple(&id, "User"))?;
+    }
 
-    let reputation = reputation.context("`Reputation` not found in attributes")?;
-    let triple = id_to_str(&id, "user.reputation", &reputation);
-    formatter.format(&triple)?;
+    if !state.opts.is_sentinel("user.reputation", &reputation) {
+        let triple = id_to_integer(&mut state.warnings, &id, "user.reputation", &reputation);
+        formatter.format(&triple)?;
+    }
 
-    let creation_date = creation_date.context("`CreationDate` not found in attributes")?;
-    let triple = id_to_str(&id, "user.creation_date", &creation_date);
+    let creation_date = creation_date
+        .or_else(|| state.opts.default_value("user.creation_date"))
+        .context("`CreationDate` not found in attributes")?;
+    let triple = id_to_datetime(
+        &mut state.warnings,
+        &id,
+        "user.creation_date",
+        &creation_date,
+    );
     formatter.format(&triple)?;
+    if state.opts.emit_year {
+        if let Some(triple) = id_to_year(&id, "user.creation_year", &creation_date) {
+            formatter.format(&triple)?;
+        }
+    }
 
-    let display_name = display_name.context("`Displayname` not found in attributes")?;
+    let mut display_name = display_name
+        .or_else(|| state.opts.default_value("user.display_name"))
+        .context("`DisplayName` not found in attributes")?;
+    if state.opts.anonymize {
+        display_name = user_pseudonym(&id[state.opts.prefix("user").len()..]).into();
+    }
     let triple = id_to_str(&id, "user.display_name", &display_name);
     formatter.format(&triple)?;
 
-    let last_access_date = last_access_date.context("`LastAccessDate` not found in attributes")?;
-    let triple = id_to_str(&id, "user.last_access_date", &last_access_date);
+    let last_access_date = last_access_date
+        .or_else(|| state.opts.default_value("user.last_access_date"))
+        .context("`LastAccessDate` not found in attributes")?;
+    let triple = id_to_datetime(
+        &mut state.warnings,
+        &id,
+        "user.last_access_date",
+        &last_access_date,
+    );
     formatter.format(&triple)?;
 
     if let Some(website_url) = website_url {
-        let triple = id_to_str(&id, "user.website_url", &website_url);
+        let as_iri = state.opts.urls_as_iris;
+        let triple = id_to_url(
+            &mut state.warnings,
+            &id,
+            "user.website_url",
+            &website_url,
+            as_iri,
+        );
         formatter.format(&triple)?;
     }
 
@@ -645,41 +3194,215 @@ fn write_user<W: Write>(formatter: &mut NTriplesFormatter<W>, e: &BytesStart) ->
     }
 
     if let Some(about_me) = about_me {
-        let triple = id_to_str(&id, "user.about_me", &about_me);
+        let about_me = state.opts.normalize_text(about_me);
+        let triple = id_to_lang_str(&id, "user.about_me", &about_me, state.opts.lang());
         formatter.format(&triple)?;
+
+        if state.opts.about_me_text {
+            let about_me_text = html_to_text(&about_me);
+            let triple =
+                id_to_lang_str(&id, "user.about_me_text", &about_me_text, state.opts.lang());
+            formatter.format(&triple)?;
+        }
     }
 
-    let views = views.context("`Views` not found in attributes")?;
-    let triple = id_to_str(&id, "user.views", &views);
-    formatter.format(&triple)?;
+    let views = views
+        .or_else(|| state.opts.default_value("user.views"))
+        .context("`Views` not found in attributes")?;
+    if !state.opts.is_sentinel("user.views", &views) {
+        let triple = id_to_integer(&mut state.warnings, &id, "user.views", &views);
+        formatter.format(&triple)?;
+    }
 
-    let upvotes = upvotes.context("`Upvotes` not found in attributes")?;
-    let triple = id_to_str(&id, "user.upvotes", &upvotes);
-    formatter.format(&triple)?;
+    let upvotes = upvotes
+        .or_else(|| state.opts.default_value("user.upvotes"))
+        .context("`UpVotes` not found in attributes")?;
+    if !state.opts.is_sentinel("user.upvotes", &upvotes) {
+        let triple = id_to_integer(&mut state.warnings, &id, "user.upvotes", &upvotes);
+        formatter.format(&triple)?;
+    }
 
-    let downvotes = downvotes.context("`Upvotes` not found in attributes")?;
-    let triple = id_to_str(&id, "user.upvotes", &downvotes);
-    formatter.format(&triple)?;
+    let downvotes = downvotes
+        .or_else(|| state.opts.default_value("user.downvotes"))
+        .context("`DownVotes` not found in attributes")?;
+    if !state.opts.is_sentinel("user.downvotes", &downvotes) {
+        let triple = id_to_integer(&mut state.warnings, &id, "user.downvotes", &downvotes);
+        formatter.format(&triple)?;
+    }
 
     if let Some(profile_image_url) = profile_image_url {
-        let triple = id_to_str(&id, "user.profile_image_url", &profile_image_url);
+        let (iri, as_iri) = ("user.profile_image_url", state.opts.urls_as_iris);
+        let triple = id_to_url(&mut state.warnings, &id, iri, &profile_image_url, as_iri);
         formatter.format(&triple)?;
     }
 
+    let account_id = account_id.filter(|v| !state.opts.is_sentinel("user.account_id", v));
     if let Some(account_id) = account_id {
-        let triple = id_to_str(&id, "user.account_id", &account_id);
+        let triple = id_to_integer(&mut state.warnings, &id, "user.account_id", &account_id);
         formatter.format(&triple)?;
     }
 
-    Ok(())
+    write_unknown(formatter, &id, "user", &unknown)
+}
+
+fn write_vote(state: &mut State, formatter: &mut dyn Formatter, row: &Row) -> Result<()> {
+    let mut id = None;
+    let mut post_id = None;
+    let mut vote_type = None;
+    let mut user_id = None;
+    let mut bounty_amount = None;
+    let mut creation_date = None;
+
+    let mut unknown = Vec::new();
+
+    for attribute in row.attributes() {
+        let (key, value) = attribute?;
+        match key {
+            b"Id" => &mut id,
+            b"PostId" => &mut post_id,
+            b"VoteTypeId" => &mut vote_type,
+            b"UserId" => &mut user_id,
+            b"BountyAmount" => &mut bounty_amount,
+            b"CreationDate" => &mut creation_date,
+            _ => {
+                if state.unknown_attribute("Votes", key) {
+                    unknown.push((key, value));
+                }
+                continue;
+            }
+        }
+        .replace(value);
+    }
+
+    let id = id.context("`Id` not found in attributes")?;
+    let id = prefixed(state.opts.prefix("vote"), &id);
+    if state.excludes(&id) || state.opts.outside_window(creation_date.as_deref()) {
+        return Ok(());
+    }
+
+    if state.opts.emit_types {
+        formatter.format(&type_triple(&id, "Vote"))?;
+    }
+
+    let post_id = post_id
+        .or_else(|| state.opts.default_value("vote.post"))
+        .context("`PostId` not found in attributes")?;
+    if !state.opts.is_sentinel("vote.post", &post_id) {
+        let post_id = prefixed(state.opts.prefix("post"), &post_id);
+        let triple = id_to_id(&id, "vote.post", &post_id);
+        formatter.format(&triple)?;
+    }
+
+    let vote_type = vote_type
+        .or_else(|| state.opts.default_value("vote.type"))
+        .context("`VoteTypeId` not found in attributes")?;
+    if !state.opts.is_sentinel("vote.type", &vote_type) {
+        let triple = id_to_integer(&mut state.warnings, &id, "vote.type", &vote_type);
+        formatter.format(&triple)?;
+    }
+
+    // Only favorites and bounties have a user, since votes are anonymous.
+    if let Some(user_id) = user_id.filter(|v| !state.opts.is_sentinel("vote.user", v)) {
+        let user_id = prefixed(state.opts.prefix("user"), &user_id);
+        let triple = id_to_id(&id, "vote.user", &user_id);
+        formatter.format(&triple)?;
+    }
+
+    if let Some(bounty_amount) =
+        bounty_amount.filter(|v| !state.opts.is_sentinel("vote.bounty_amount", v))
+    {
+        let triple = id_to_integer(
+            &mut state.warnings,
+            &id,
+            "vote.bounty_amount",
+            &bounty_amount,
+        );
+        formatter.format(&triple)?;
+    }
+
+    let creation_date = creation_date
+        .or_else(|| state.opts.default_value("vote.creation_date"))
+        .context("`CreationDate` not found in attributes")?;
+    let triple = id_to_datetime(
+        &mut state.warnings,
+        &id,
+        "vote.creation_date",
+        &creation_date,
+    );
+    formatter.format(&triple)?;
+
+    write_unknown(formatter, &id, "vote", &unknown)
+}
+
+/// Names the bucket that `view_count` falls in, e.g. `<100`, `100-1k` or
+/// `>10k` for the thresholds `100,1000,10000`.
+fn view_bucket(view_count: u64, thresholds: &[u64]) -> String {
+    let idx = thresholds.partition_point(|&threshold| threshold <= view_count);
+    match (
+        idx.checked_sub(1).map(|idx| thresholds[idx]),
+        thresholds.get(idx),
+    ) {
+        (None, Some(&upper)) => format!("<{}", short_count(upper)),
+        (Some(lower), Some(&upper)) => format!("{}-{}", short_count(lower), short_count(upper)),
+        (Some(lower), None) => format!(">{}", short_count(lower)),
+        (None, None) => "all".to_string(),
+    }
+}
+
+/// Formats round numbers compactly, e.g. `1000` as `1k` and `2500000` as
+/// `2500k`.
+fn short_count(count: u64) -> String {
+    if count >= 1_000_000 && count.is_multiple_of(1_000_000) {
+        format!("{}M", count / 1_000_000)
+    } else if count >= 1000 && count.is_multiple_of(1000) {
+        format!("{}k", count / 1000)
+    } else {
+        count.to_string()
+    }
+}
+
+/// Splits a `Tags` attribute of the form `<a><b><c>` into tag names. An empty
+/// value has no tags, and a value that is not a sequence of `<name>`s is
+/// rejected as a whole, rather than guessing where its tags start and end.
+fn split_tags(tags: &str) -> Option<Vec<&str>> {
+    if tags.is_empty() {
+        return Some(Vec::new());
+    }
+    let inner = tags.strip_prefix('<')?.strip_suffix('>')?;
+    let tags = inner.split("><").collect::<Vec<_>>();
+    if tags
+        .iter()
+        .any(|tag| tag.is_empty() || tag.contains(['<', '>']))
+    {
+        return None;
+    }
+    Some(tags)
 }
 
-fn parse_attribute(attribute: Attribute) -> Result<String> {
-    let attribute = attribute
-        .unescaped_value()
-        .context("error escaping attribute value")?;
+fn tag_id(prefix: &str, name: &str) -> String {
+    let mut id = String::with_capacity(prefix.len() + BASE32_NOPAD.encode_len(name.len()));
+    id.push_str(prefix);
+    BASE32_NOPAD.encode_append(name.as_bytes(), &mut id);
+    id
+}
 
-    String::from_utf8(attribute.into()).context("invalid utf-8 in attribute value")
+/// A tag id that keeps the letters, digits and dashes of the name, for
+/// `--readable-tag-ids`. Other bytes are written as a `.` and two hex digits,
+/// e.g. `tc.23` for `c#` and `tasp.2Enet` for `asp.net`, so that different
+/// names never get the same id and ids can be decoded back. Like the base32
+/// ids, they never contain a `_`, which separates the tags of a co-occurrence.
+fn readable_tag_id(prefix: &str, name: &str) -> String {
+    let mut id = String::with_capacity(prefix.len() + name.len());
+    id.push_str(prefix);
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' {
+            id.push(byte as char);
+        } else {
+            id.push('.');
+            HEXUPPER.encode_append(&[byte], &mut id);
+        }
+    }
+    id
 }
 
 fn id_to_str<'a>(id: &'a str, iri: &'a str, value: &'a str) -> Triple<'a> {
@@ -690,6 +3413,179 @@ fn id_to_str<'a>(id: &'a str, iri: &'a str, value: &'a str) -> Triple<'a> {
     }
 }
 
+/// Free text, tagged with `language` if it is set.
+fn id_to_lang_str<'a>(
+    id: &'a str,
+    iri: &'a str,
+    value: &'a str,
+    language: Option<&'a str>,
+) -> Triple<'a> {
+    let language = match language {
+        Some(language) => language,
+        None => return id_to_str(id, iri, value),
+    };
+    Triple {
+        subject: BlankNode { id }.into(),
+        predicate: NamedNode { iri },
+        object: Literal::LanguageTaggedString { value, language }.into(),
+    }
+}
+
+fn id_to_typed<'a>(id: &'a str, iri: &'a str, value: &'a str, datatype: &'a str) -> Triple<'a> {
+    Triple {
+        subject: BlankNode { id }.into(),
+        predicate: NamedNode { iri },
+        object: Literal::Typed {
+            value,
+            datatype: NamedNode { iri: datatype },
+        }
+        .into(),
+    }
+}
+
+/// Writes the attributes that are not known for `--passthrough-unknown`, as
+/// strings named `<kind>.x_` followed by the attribute name in snake case.
+fn write_unknown(
+    formatter: &mut dyn Formatter,
+    id: &str,
+    kind: &str,
+    unknown: &[(&[u8], Cow<str>)],
+) -> Result<()> {
+    for (key, value) in unknown {
+        let iri = format!("{}.x_{}", kind, snake_case(&String::from_utf8_lossy(key)));
+        formatter.format(&id_to_str(id, &iri, value))?;
+    }
+    Ok(())
+}
+
+/// The name of an attribute in lower snake case, e.g. `bounty_amount` for
+/// `BountyAmount` and `about_me_html` for `AboutMeHTML`. Characters other than
+/// ASCII letters and digits become `_`.
+fn snake_case(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_ascii_alphanumeric() {
+            snake.push('_');
+            continue;
+        }
+        if c.is_ascii_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|c| c.is_ascii_lowercase());
+            if prev.is_ascii_lowercase()
+                || prev.is_ascii_digit()
+                || prev.is_ascii_uppercase() && next_is_lower
+            {
+                snake.push('_');
+            }
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+/// A date as an `xsd:dateTime`. Dates that are not valid are written as
+/// plain strings instead, with a warning.
+fn id_to_datetime<'a>(
+    warnings: &mut Warnings,
+    id: &'a str,
+    iri: &'a str,
+    value: &'a str,
+) -> Triple<'a> {
+    if !is_datetime(value) {
+        warnings.warn(
+            Category::InvalidDate,
+            format_args!("{}: invalid `{}` {:?}, writing it untyped", id, iri, value),
+        );
+        return id_to_str(id, iri, value);
+    }
+    id_to_typed(id, iri, value, XSD_DATETIME)
+}
+
+/// The year of a date as an `xsd:gYear`, for `--emit-year`. Dates that are
+/// not valid get no year, and are written untyped by `id_to_datetime`.
+fn id_to_year<'a>(id: &'a str, iri: &'a str, value: &'a str) -> Option<Triple<'a>> {
+    year_of(value).map(|year| id_to_typed(id, iri, year, XSD_GYEAR))
+}
+
+/// A number as an `xsd:integer`. Values that are not integers are written as
+/// plain strings instead, with a warning.
+fn id_to_integer<'a>(
+    warnings: &mut Warnings,
+    id: &'a str,
+    iri: &'a str,
+    value: &'a str,
+) -> Triple<'a> {
+    if !is_integer(value) {
+        warnings.warn(
+            Category::InvalidInteger,
+            format_args!("{}: invalid `{}` {:?}, writing it untyped", id, iri, value),
+        );
+        return id_to_str(id, iri, value);
+    }
+    id_to_typed(id, iri, value, XSD_INTEGER)
+}
+
+/// A flag as an `xsd:boolean`, from `true`, `false`, `1` or `0`. Other values
+/// are written as plain strings instead, with a warning.
+fn id_to_boolean<'a>(
+    warnings: &mut Warnings,
+    id: &'a str,
+    iri: &'a str,
+    value: &'a str,
+) -> Triple<'a> {
+    let value = match value {
+        "true" | "1" => "true",
+        "false" | "0" => "false",
+        _ => {
+            warnings.warn(
+                Category::InvalidBoolean,
+                format_args!("{}: invalid `{}` {:?}, writing it untyped", id, iri, value),
+            );
+            return id_to_str(id, iri, value);
+        }
+    };
+    id_to_typed(id, iri, value, XSD_BOOLEAN)
+}
+
+/// A URL as an `xsd:anyURI`, or as an IRI with `--urls-as-iris`. URLs that are
+/// not absolute IRIs, e.g. without a scheme, are written as plain strings
+/// instead, with a warning.
+fn id_to_url<'a>(
+    warnings: &mut Warnings,
+    id: &'a str,
+    iri: &'a str,
+    value: &'a str,
+    as_iri: bool,
+) -> Triple<'a> {
+    if !is_uri(value) {
+        warnings.warn(
+            Category::InvalidUrl,
+            format_args!("{}: invalid `{}` {:?}, writing it untyped", id, iri, value),
+        );
+        return id_to_str(id, iri, value);
+    }
+    if as_iri {
+        return id_to_iri(id, iri, value);
+    }
+    id_to_typed(id, iri, value, XSD_ANY_URI)
+}
+
+/// A relative IRI object such as `LinkedLink`, which is moved under the
+/// predicate namespace like the predicates, or a full IRI such as a URL.
+fn id_to_iri<'a>(id: &'a str, iri: &'a str, iri_obj: &'a str) -> Triple<'a> {
+    Triple {
+        subject: BlankNode { id }.into(),
+        predicate: NamedNode { iri },
+        object: NamedNode { iri: iri_obj }.into(),
+    }
+}
+
+/// The `rdf:type` of an entity, such as `User`, for `--emit-types`.
+fn type_triple<'a>(id: &'a str, class: &'a str) -> Triple<'a> {
+    id_to_iri(id, RDF_TYPE, class)
+}
+
 fn id_to_id<'a>(id: &'a str, iri: &'a str, id_obj: &'a str) -> Triple<'a> {
     Triple {
         subject: BlankNode { id }.into(),
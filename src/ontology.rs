@@ -0,0 +1,317 @@
+use crate::datatype::{XSD_ANY_URI, XSD_BOOLEAN, XSD_DATETIME, XSD_GYEAR, XSD_INTEGER};
+use crate::formatter::PREDICATE_NAMESPACE;
+use crate::inverse::INVERSES;
+use crate::types::{LINK_TYPES, POST_HISTORY_TYPES, POST_TYPES};
+use crate::Opts;
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+const XSD_STRING: &str = "http://www.w3.org/2001/XMLSchema#string";
+const RDF_LANG_STRING: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#langString";
+const RDFS_RESOURCE: &str = "http://www.w3.org/2000/01/rdf-schema#Resource";
+
+/// What the objects of a predicate are.
+#[derive(Clone, Copy)]
+enum Range {
+    /// Literals of an XSD datatype.
+    Datatype(&'static str),
+    /// Free text, which is language tagged with `--lang`.
+    Text,
+    /// URLs, which are IRIs with `--urls-as-iris`.
+    Url,
+    /// Nodes of a class.
+    Class(&'static str),
+}
+
+use Range::{Class, Datatype, Text, Url};
+
+const BOOLEAN: Range = Datatype(XSD_BOOLEAN);
+const DATETIME: Range = Datatype(XSD_DATETIME);
+const GYEAR: Range = Datatype(XSD_GYEAR);
+const INTEGER: Range = Datatype(XSD_INTEGER);
+const STRING: Range = Datatype(XSD_STRING);
+
+/// Every predicate that is written, with the class of its subjects and its
+/// range. This mirrors the schemas in `res/`, which also give the class of
+/// every predicate.
+const PREDICATES: &[(&str, &str, Range)] = &[
+    ("badge.user", "Badge", Class("User")),
+    ("badge.name", "Badge", STRING),
+    ("badge.date", "Badge", DATETIME),
+    ("badge.class", "Badge", INTEGER),
+    ("badge.tag_based", "Badge", BOOLEAN),
+    ("comment.post", "Comment", Class("Post")),
+    ("comment.score", "Comment", INTEGER),
+    ("comment.text", "Comment", Text),
+    ("comment.creation_date", "Comment", DATETIME),
+    ("comment.creation_year", "Comment", GYEAR),
+    ("comment.user", "Comment", Class("User")),
+    ("comment.user_display_name", "Comment", STRING),
+    ("comment.content_license", "Comment", STRING),
+    ("post.type", "Post", INTEGER),
+    ("post.accepted_answer", "Post", Class("Post")),
+    ("post.parent", "Post", Class("Post")),
+    ("post.answer_rank", "Post", INTEGER),
+    ("post.is_accepted", "Post", BOOLEAN),
+    ("post.creation_date", "Post", DATETIME),
+    ("post.creation_year", "Post", GYEAR),
+    ("post.deletion_date", "Post", DATETIME),
+    ("post.score", "Post", INTEGER),
+    ("post.view_count", "Post", INTEGER),
+    ("post.view_bucket", "Post", STRING),
+    ("post.body", "Post", Text),
+    ("post.owner", "Post", Class("User")),
+    ("post.owner_display_name", "Post", STRING),
+    ("post.last_editor", "Post", Class("User")),
+    ("post.last_editor_display_name", "Post", STRING),
+    ("post.last_edit_date", "Post", DATETIME),
+    ("post.last_activity_date", "Post", DATETIME),
+    ("post.title", "Post", Text),
+    ("post.tags", "Post", Class("Tag")),
+    ("post.tag_name", "Post", STRING),
+    ("post.answer_count", "Post", INTEGER),
+    ("post.comment_count", "Post", INTEGER),
+    ("post.favorite_count", "Post", INTEGER),
+    ("post.closed_date", "Post", DATETIME),
+    ("post.community_owned_date", "Post", DATETIME),
+    ("post.content_license", "Post", STRING),
+    ("post.content_hash", "Post", STRING),
+    ("post.comment", "Post", Class("Comment")),
+    ("post.accepted_answer_of", "Post", Class("Post")),
+    ("post.answer", "Post", Class("Post")),
+    ("post.history", "Post", Class("PostHistory")),
+    ("post.link", "Post", Class("PostLink")),
+    ("post.related_link", "Post", Class("PostLink")),
+    ("post.excerpt_of", "Post", Class("Tag")),
+    ("post.wiki_of", "Post", Class("Tag")),
+    ("post.edited_by", "Post", Class("User")),
+    ("post.vote", "Post", Class("Vote")),
+    ("posthistory.type", "PostHistory", INTEGER),
+    ("posthistory.post", "PostHistory", Class("Post")),
+    ("posthistory.revision_guid", "PostHistory", STRING),
+    ("posthistory.creation_date", "PostHistory", DATETIME),
+    ("posthistory.user", "PostHistory", Class("User")),
+    ("posthistory.user_display_name", "PostHistory", STRING),
+    ("posthistory.comment", "PostHistory", STRING),
+    ("posthistory.text", "PostHistory", Text),
+    ("posthistory.content_license", "PostHistory", STRING),
+    ("postlink.creation_date", "PostLink", DATETIME),
+    ("postlink.post", "PostLink", Class("Post")),
+    ("postlink.related_post", "PostLink", Class("Post")),
+    ("postlink.link_type", "PostLink", INTEGER),
+    ("postlink.link_type_id", "PostLink", INTEGER),
+    ("tag.name", "Tag", STRING),
+    ("tag.count", "Tag", INTEGER),
+    ("tag.excerpt_post", "Tag", Class("Post")),
+    ("tag.wiki_post", "Tag", Class("Post")),
+    ("tag.cooccurs_with", "Tag", Class("Tag")),
+    ("tag.post_count", "Tag", INTEGER),
+    ("tag.post", "Tag", Class("Post")),
+    ("tag.cooccurrence", "Tag", Class("TagCooccurrence")),
+    ("cooccurrence.tag", "TagCooccurrence", Class("Tag")),
+    ("cooccurrence.weight", "TagCooccurrence", INTEGER),
+    ("user.reputation", "User", INTEGER),
+    ("user.creation_date", "User", DATETIME),
+    ("user.creation_year", "User", GYEAR),
+    ("user.display_name", "User", STRING),
+    ("user.last_access_date", "User", DATETIME),
+    ("user.website_url", "User", Url),
+    ("user.location", "User", STRING),
+    ("user.about_me", "User", Text),
+    ("user.about_me_text", "User", Text),
+    ("user.views", "User", INTEGER),
+    ("user.upvotes", "User", INTEGER),
+    ("user.downvotes", "User", INTEGER),
+    ("user.profile_image_url", "User", Url),
+    ("user.account_id", "User", INTEGER),
+    ("user.edited", "User", Class("Post")),
+    ("user.badge", "User", Class("Badge")),
+    ("user.post_count", "User", INTEGER),
+    ("user.comment", "User", Class("Comment")),
+    ("user.post", "User", Class("Post")),
+    ("user.last_edited_post", "User", Class("Post")),
+    ("user.post_history", "User", Class("PostHistory")),
+    ("user.vote", "User", Class("Vote")),
+    ("vote.post", "Vote", Class("Post")),
+    ("vote.type", "Vote", INTEGER),
+    ("vote.user", "Vote", Class("User")),
+    ("vote.bounty_amount", "Vote", INTEGER),
+    ("vote.creation_date", "Vote", DATETIME),
+    ("stats.badges", "Stats", INTEGER),
+    ("stats.comments", "Stats", INTEGER),
+    ("stats.posts", "Stats", INTEGER),
+    ("stats.post_history", "Stats", INTEGER),
+    ("stats.post_links", "Stats", INTEGER),
+    ("stats.tags", "Stats", INTEGER),
+    ("stats.users", "Stats", INTEGER),
+    ("stats.votes", "Stats", INTEGER),
+    ("statement.source", "Statement", STRING),
+    ("statement.record", "Statement", STRING),
+];
+
+/// Writes an RDFS/OWL ontology in Turtle for `--emit-schema`, declaring every
+/// class and predicate under the predicate namespace, with the predicates
+/// named in `--predicate-style`. Free text has the range `rdf:langString` with
+/// `--lang`, URLs are resources with `--urls-as-iris`, the post and revision
+/// types are subclasses with `--type-names`, and the link types are
+/// individuals with `--link-type-names`. The reverse relations of
+/// `--emit-inverse` are declared as the inverses of their relations.
+pub fn write_ontology(path: &Path, opts: &Opts) -> Result<()> {
+    let namespace = match opts.predicate_base.as_str() {
+        "" => PREDICATE_NAMESPACE,
+        base => base,
+    };
+    let range_of = |predicate: &str, range: Range| match predicate {
+        "postlink.link_type" if opts.link_type_names => Class("LinkType"),
+        _ => range,
+    };
+
+    let file =
+        File::create(path).with_context(|| format!("could not create {}", path.display()))?;
+    let mut write = BufWriter::new(file);
+
+    writeln!(write, "@prefix owl: <http://www.w3.org/2002/07/owl#> .")?;
+    writeln!(
+        write,
+        "@prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> ."
+    )?;
+    writeln!(write)?;
+
+    let mut classes: Vec<&str> = PREDICATES.iter().map(|&(_, class, _)| class).collect();
+    for &(predicate, _, range) in PREDICATES {
+        if let Class(class) = range_of(predicate, range) {
+            classes.push(class);
+        }
+    }
+    classes.sort_unstable();
+    classes.dedup();
+    for class in classes {
+        writeln!(write, "<{}{}> a owl:Class ;", namespace, class)?;
+        writeln!(write, "    rdfs:label \"{}\" .", class)?;
+        writeln!(write)?;
+    }
+
+    if opts.type_names {
+        let types = POST_TYPES.iter().map(|&(_, name)| (name, "Post"));
+        let history_types = POST_HISTORY_TYPES
+            .iter()
+            .map(|&(_, name)| (name, "PostHistory"));
+        for (name, superclass) in types.chain(history_types) {
+            writeln!(write, "<{}{}> a owl:Class ;", namespace, name)?;
+            writeln!(write, "    rdfs:subClassOf <{}{}> ;", namespace, superclass)?;
+            writeln!(write, "    rdfs:label \"{}\" .", name)?;
+            writeln!(write)?;
+        }
+    }
+
+    if opts.link_type_names {
+        for &(_, name) in LINK_TYPES {
+            writeln!(
+                write,
+                "<{}{}> a owl:NamedIndividual, <{}LinkType> ;",
+                namespace, name, namespace
+            )?;
+            writeln!(write, "    rdfs:label \"{}\" .", name)?;
+            writeln!(write)?;
+        }
+    }
+
+    for &(predicate, class, range) in PREDICATES {
+        let range = range_of(predicate, range);
+        let predicate = opts.predicate_style.apply(predicate);
+        let (kind, range) = match range {
+            Datatype(datatype) => ("DatatypeProperty", datatype.to_string()),
+            Text if opts.lang.is_some() => ("DatatypeProperty", RDF_LANG_STRING.to_string()),
+            Text => ("DatatypeProperty", XSD_STRING.to_string()),
+            Url if opts.urls_as_iris => ("ObjectProperty", RDFS_RESOURCE.to_string()),
+            Url => ("DatatypeProperty", XSD_ANY_URI.to_string()),
+            Class(range) => ("ObjectProperty", format!("{}{}", namespace, range)),
+        };
+        writeln!(write, "<{}{}> a owl:{} ;", namespace, predicate, kind)?;
+        writeln!(write, "    rdfs:label \"{}\" ;", predicate)?;
+        writeln!(write, "    rdfs:domain <{}{}> ;", namespace, class)?;
+        writeln!(write, "    rdfs:range <{}> .", range)?;
+        writeln!(write)?;
+    }
+
+    for &(predicate, inverse) in INVERSES {
+        let predicate = opts.predicate_style.apply(predicate);
+        let inverse = opts.predicate_style.apply(inverse);
+        writeln!(
+            write,
+            "<{}{}> owl:inverseOf <{}{}> .",
+            namespace, inverse, namespace, predicate
+        )?;
+    }
+
+    write
+        .flush()
+        .with_context(|| format!("could not write {}", path.display()))
+}
+
+/// Prints every predicate for the `schema` subcommand, with the class of its
+/// subjects and what its objects are: literals of a datatype, or relations to
+/// nodes of a class. With `json`, it is printed as a JSON array of objects
+/// such as `{"predicate": "comment.post", "entity": "Comment", "kind":
+/// "relation", "target": "Post", "inverse": "post.comment"}`, and otherwise as
+/// a table with a column for each. The names and datatypes are the defaults,
+/// without `--predicate-style`, `--lang`, `--urls-as-iris` or
+/// `--link-type-names`.
+pub fn print_schema(json: bool) -> Result<()> {
+    let stdout = io::stdout();
+    let mut write = BufWriter::new(stdout.lock());
+
+    if json {
+        let predicates = PREDICATES
+            .iter()
+            .map(|&(predicate, class, range)| match object_of(range) {
+                ("relation", target) => json!({
+                    "predicate": predicate,
+                    "entity": class,
+                    "kind": "relation",
+                    "target": target,
+                    "inverse": INVERSES
+                        .iter()
+                        .find(|&&(relation, _)| relation == predicate)
+                        .map(|&(_, inverse)| inverse),
+                }),
+                (kind, datatype) => json!({
+                    "predicate": predicate,
+                    "entity": class,
+                    "kind": kind,
+                    "datatype": datatype,
+                    "text": matches!(range, Text),
+                }),
+            })
+            .collect::<Vec<Value>>();
+        serde_json::to_writer_pretty(&mut write, &predicates)?;
+        writeln!(write)?;
+    } else {
+        for &(predicate, class, range) in PREDICATES {
+            let (kind, object) = object_of(range);
+            writeln!(
+                write,
+                "{:<32} {:<16} {:<8} {}",
+                predicate, class, kind, object
+            )?;
+        }
+    }
+
+    write.flush().context("could not write to stdout")
+}
+
+/// Whether the objects of a range are literals or relations by default, i.e.
+/// without `--lang` and `--urls-as-iris`, with their datatype or class.
+fn object_of(range: Range) -> (&'static str, &'static str) {
+    match range {
+        Datatype(datatype) => ("literal", datatype),
+        Text => ("literal", XSD_STRING),
+        Url => ("literal", XSD_ANY_URI),
+        Class(class) => ("relation", class),
+    }
+}
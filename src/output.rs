@@ -0,0 +1,174 @@
+use clap::ArgEnum;
+use flate2::write::GzEncoder;
+use gzp::deflate::Gzip;
+use gzp::par::compress::{ParCompress, ParCompressBuilder};
+use gzp::ZWriter;
+use zstd::stream::write::Encoder as ZstdEncoder;
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+/// A writer that has to be finished explicitly, e.g. to write a trailer.
+pub trait Output: Write {
+    fn finish(self) -> io::Result<()>;
+}
+
+impl<W: Write> Output for GzEncoder<W> {
+    fn finish(self) -> io::Result<()> {
+        GzEncoder::finish(self)?.flush()
+    }
+}
+
+impl Output for ParCompress<Gzip> {
+    fn finish(mut self) -> io::Result<()> {
+        ZWriter::finish(&mut self).map_err(io::Error::other)
+    }
+}
+
+impl<W: Write> Output for ZstdEncoder<'_, W> {
+    fn finish(self) -> io::Result<()> {
+        ZstdEncoder::finish(self)?.flush()
+    }
+}
+
+#[derive(ArgEnum, Clone, Copy, PartialEq)]
+pub enum Compression {
+    Gzip,
+    /// Compresses better than gzip at its default level, and decompresses
+    /// much faster.
+    Zstd,
+    /// Writes the output uncompressed, e.g. for debugging and diffing.
+    None,
+}
+
+impl Compression {
+    pub fn extension(self) -> Option<&'static str> {
+        match self {
+            Compression::Gzip => Some("gz"),
+            Compression::Zstd => Some("zst"),
+            Compression::None => None,
+        }
+    }
+
+    /// The supported levels. Level 0 of gzip stores the data uncompressed.
+    pub fn levels(self) -> RangeInclusive<u32> {
+        match self {
+            Compression::Gzip => 0..=9,
+            Compression::Zstd => 1..=22,
+            Compression::None => 0..=0,
+        }
+    }
+
+    pub fn default_level(self) -> u32 {
+        match self {
+            Compression::Gzip => 9,
+            Compression::Zstd => 19,
+            Compression::None => 0,
+        }
+    }
+}
+
+/// The path that writes to stdout instead of a file.
+pub fn is_stdout(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+/// An output file or stdout.
+pub type Sink = BufWriter<Box<dyn Write + Send>>;
+
+/// An output file, compressed or not.
+pub enum Encoder {
+    Gzip(GzEncoder<Sink>),
+    /// Gzip compressed on several threads, for `--compress-threads`.
+    ParGzip(ParCompress<Gzip>),
+    Zstd(ZstdEncoder<'static, Sink>),
+    None(Sink),
+}
+
+impl Write for Encoder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            Encoder::ParGzip(encoder) => encoder.write(buf),
+            Encoder::Zstd(encoder) => encoder.write(buf),
+            Encoder::None(file) => file.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Gzip(encoder) => encoder.flush(),
+            Encoder::ParGzip(encoder) => encoder.flush(),
+            Encoder::Zstd(encoder) => encoder.flush(),
+            Encoder::None(file) => file.flush(),
+        }
+    }
+}
+
+impl Output for Encoder {
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Encoder::Gzip(encoder) => Output::finish(encoder),
+            Encoder::ParGzip(encoder) => Output::finish(encoder),
+            Encoder::Zstd(encoder) => Output::finish(encoder),
+            // Without a trailer to write, the buffer still has to be flushed.
+            Encoder::None(mut file) => file.flush(),
+        }
+    }
+}
+
+/// Creates an output file, or writes to stdout if the path is `-`. With
+/// `append`, an existing file is kept and the output is written after it as a
+/// new gzip member or zstd frame. The compressed output is buffered in
+/// `capacity` bytes. Gzip is compressed on `threads` threads if there are more
+/// than one, as a single gzip member either way.
+pub fn open_output(
+    path: &Path,
+    compression: Compression,
+    level: u32,
+    append: bool,
+    capacity: usize,
+    threads: usize,
+) -> io::Result<Encoder> {
+    let file: Box<dyn Write + Send> = if is_stdout(path) {
+        Box::new(io::stdout())
+    } else if append {
+        Box::new(OpenOptions::new().create(true).append(true).open(path)?)
+    } else {
+        Box::new(File::create(path)?)
+    };
+    let file = BufWriter::with_capacity(capacity, file);
+    let gzip_level = flate2::Compression::new(level);
+    Ok(match compression {
+        Compression::Gzip if threads > 1 => Encoder::ParGzip(
+            ParCompressBuilder::new()
+                .num_threads(threads)
+                .map_err(io::Error::other)?
+                .compression_level(gzip_level)
+                .from_writer(file),
+        ),
+        Compression::Gzip => Encoder::Gzip(GzEncoder::new(file, gzip_level)),
+        Compression::Zstd => Encoder::Zstd(ZstdEncoder::new(file, level as i32)?),
+        Compression::None => Encoder::None(file),
+    })
+}
+
+/// Concatenates the output files `parts` into `path`, or stdout if it is `-`,
+/// removing them. With `append`, they are written after the existing contents
+/// of `path`.
+pub fn concatenate(parts: &[PathBuf], path: &Path, append: bool) -> io::Result<()> {
+    let mut file: Box<dyn Write> = if is_stdout(path) {
+        Box::new(io::stdout())
+    } else if append {
+        Box::new(OpenOptions::new().create(true).append(true).open(path)?)
+    } else {
+        Box::new(File::create(path)?)
+    };
+    for part in parts {
+        io::copy(&mut File::open(part)?, &mut file)?;
+        fs::remove_file(part)?;
+    }
+    file.flush()
+}
@@ -0,0 +1,151 @@
+use crate::formatter::{Formatter, OutputFormat};
+use crate::output::{open_output, Compression, Encoder};
+
+use anyhow::{bail, Context, Result};
+use log::info;
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+
+/// Creates the formatter of a per-tag file, like that of the main output.
+pub type NewFormatter<'a> = Box<dyn Fn(Encoder) -> Result<Box<dyn Formatter + Send>> + Send + 'a>;
+
+/// Size of the buffer of every per-tag file. It is kept small, since there
+/// can be many of them open, and they are compressed on a single thread for
+/// the same reason.
+const BUFFER_SIZE: usize = 8 * 1024;
+
+/// One compressed output file per tag, for `--posts-by-tag`.
+///
+/// Only `max_open` files are kept open at a time. When another one is needed,
+/// the least recently used file is finished and closed; if its tag shows up
+/// again, the file is reopened in append mode and continued as a new gzip
+/// member or zstd frame, for the formats that can be concatenated.
+pub struct TagPartitions<'a> {
+    dir: PathBuf,
+    format: OutputFormat,
+    compression: Compression,
+    level: u32,
+    max_open: usize,
+    append: bool,
+    new_formatter: NewFormatter<'a>,
+    open: HashMap<String, (u64, Box<dyn Formatter + Send>)>,
+    created: HashSet<String>,
+    tick: u64,
+}
+
+impl<'a> TagPartitions<'a> {
+    pub fn new(
+        dir: PathBuf,
+        format: OutputFormat,
+        compression: Compression,
+        level: u32,
+        max_open: usize,
+        append: bool,
+        new_formatter: NewFormatter<'a>,
+    ) -> Result<Self> {
+        fs::create_dir_all(&dir).context("could not create --posts-by-tag directory")?;
+        Ok(TagPartitions {
+            dir,
+            format,
+            compression,
+            level,
+            max_open: max_open.max(1),
+            append,
+            new_formatter,
+            open: HashMap::new(),
+            created: HashSet::new(),
+            tick: 0,
+        })
+    }
+
+    /// Returns the formatters of the given tags, opening their files if needed.
+    pub fn formatters(&mut self, tags: &[&str]) -> Result<Vec<&mut dyn Formatter>> {
+        self.tick += 1;
+        for tag in tags {
+            match self.open.get_mut(*tag) {
+                Some((last_used, _)) => *last_used = self.tick,
+                None => {
+                    self.evict(tags)?;
+                    let formatter = self.open_file(tag)?;
+                    self.open.insert(tag.to_string(), (self.tick, formatter));
+                }
+            }
+        }
+
+        let tick = self.tick;
+        Ok(self
+            .open
+            .values_mut()
+            .filter(|(last_used, _)| *last_used == tick)
+            .map(|(_, formatter)| formatter.as_mut() as &mut dyn Formatter)
+            .collect())
+    }
+
+    pub fn finish(self) -> Result<()> {
+        info!("PostsByTag: {} files", self.created.len());
+        for (_, (_, formatter)) in self.open {
+            formatter.finish()?;
+        }
+        Ok(())
+    }
+
+    /// Closes the least recently used file if the limit is reached, skipping
+    /// the files needed for the current post.
+    fn evict(&mut self, keep: &[&str]) -> Result<()> {
+        if self.open.len() < self.max_open {
+            return Ok(());
+        }
+
+        let lru = self
+            .open
+            .iter()
+            .filter(|(tag, _)| !keep.contains(&tag.as_str()))
+            .min_by_key(|(_, (last_used, _))| *last_used)
+            .map(|(tag, _)| tag.clone());
+        if let Some(tag) = lru {
+            let (_, formatter) = self.open.remove(&tag).unwrap();
+            formatter.finish()?;
+        }
+        Ok(())
+    }
+
+    fn open_file(&mut self, tag: &str) -> Result<Box<dyn Formatter + Send>> {
+        let mut name = format!("{}.{}", file_name(tag), self.format.extension());
+        if let Some(extension) = self.compression.extension() {
+            name.push('.');
+            name.push_str(extension);
+        }
+        let path = self.dir.join(name);
+        let append = self.append || self.created.contains(tag);
+        if append && !self.format.concatenates() {
+            bail!(
+                "could not reopen {}, since outputs of this format cannot be appended to, raise \
+                 --posts-by-tag-max-open above the number of tags",
+                path.display()
+            );
+        }
+        let writer = open_output(&path, self.compression, self.level, append, BUFFER_SIZE, 1)
+            .with_context(|| format!("could not open {}", path.display()))?;
+        self.created.insert(tag.to_string());
+
+        let mut formatter = (self.new_formatter)(writer)?;
+        formatter.set_graph("Posts");
+        Ok(formatter)
+    }
+}
+
+/// Percent-encodes every byte of a tag that is not safe in a file name.
+fn file_name(tag: &str) -> String {
+    let mut name = String::with_capacity(tag.len());
+    for &b in tag.as_bytes() {
+        match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'+' | b'#' => {
+                name.push(b as char)
+            }
+            _ => name.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    name
+}
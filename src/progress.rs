@@ -0,0 +1,77 @@
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+
+use std::io::{self, IsTerminal, Write};
+
+/// Live progress bars for the entities being written, one per input file.
+#[derive(Clone)]
+pub struct Progress {
+    multi: MultiProgress,
+}
+
+impl Progress {
+    /// Progress bars are only drawn on a terminal; `None` otherwise.
+    pub fn new() -> Option<Self> {
+        if !io::stderr().is_terminal() {
+            return None;
+        }
+        Some(Progress {
+            multi: MultiProgress::new(),
+        })
+    }
+
+    /// Adds a bar for an input file of `size` bytes, or a spinner if the size
+    /// is not known, e.g. for files in an archive. The position is in bytes,
+    /// so the records and their rate go in the message, see `set_records`.
+    pub fn bar(&self, name: &str, size: Option<u64>) -> ProgressBar {
+        let bar = match size {
+            Some(size) => ProgressBar::new(size).with_style(
+                ProgressStyle::with_template(
+                    "{prefix:>12} [{bar:40}] {bytes}/{total_bytes} {binary_bytes_per_sec} {msg} ({eta})",
+                )
+                .unwrap()
+                .progress_chars("=> "),
+            ),
+            None => ProgressBar::new_spinner().with_style(
+                ProgressStyle::with_template(
+                    "{prefix:>12} {spinner} {bytes} {binary_bytes_per_sec} {msg}",
+                )
+                .unwrap(),
+            ),
+        };
+        bar.set_prefix(name.to_string());
+        self.multi.add(bar)
+    }
+
+    /// A writer for the log, which hides the bars while a line is written so
+    /// that they do not garble each other.
+    pub fn log_writer(&self) -> LogWriter {
+        LogWriter {
+            multi: self.multi.clone(),
+        }
+    }
+}
+
+/// Shows the number of records read so far, and their rate, on a bar.
+pub fn set_records(bar: &ProgressBar, records: usize) {
+    let seconds = bar.elapsed().as_secs_f64();
+    let rate = if seconds > 0.0 {
+        records as f64 / seconds
+    } else {
+        0.0
+    };
+    bar.set_message(format!("{} records ({:.0}/s)", records, rate));
+}
+
+pub struct LogWriter {
+    multi: MultiProgress,
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.multi.suspend(|| io::stderr().write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
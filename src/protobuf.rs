@@ -0,0 +1,420 @@
+use crate::formatter::Formatter;
+use crate::output::Output;
+
+use anyhow::Result;
+use prost::Message;
+use rio_api::model::{Literal, NamedOrBlankNode, Term, Triple};
+
+use std::mem;
+use std::str::FromStr;
+
+/// The messages of `res/stackoverflow.proto`, generated by `build.rs`.
+#[allow(clippy::large_enum_variant)]
+pub mod proto {
+    include!(concat!(env!("OUT_DIR"), "/stackoverflow2rdf.rs"));
+}
+
+use proto::property::Object;
+use proto::record::Entity;
+use proto::{
+    Badge, Comment, Post, PostHistory, PostLink, Property, Record, Resource, Statement, Stats, Tag,
+    TagCooccurrence, User, Vote,
+};
+
+/// Writes a stream of length-delimited `Record` messages, each holding the
+/// message of its entity, e.g. a `Post`.
+///
+/// The `write_*` functions emit the triples of an entity consecutively, so
+/// triples are grouped by looking at the previous subject only. The message of
+/// a subject is picked by the first predicate of an entity, e.g. `post.score`,
+/// so triples before it, such as the `rdf:type` of `--emit-types`, wait in
+/// `pending`.
+pub struct ProtobufFormatter<W: Output> {
+    write: W,
+    id: String,
+    entity: Option<Entity>,
+    pending: Vec<Property>,
+    buf: Vec<u8>,
+}
+
+impl<W: Output> ProtobufFormatter<W> {
+    pub fn new(write: W) -> Self {
+        ProtobufFormatter {
+            write,
+            id: String::new(),
+            entity: None,
+            pending: Vec::new(),
+            buf: Vec::new(),
+        }
+    }
+
+    fn flush_record(&mut self) -> Result<()> {
+        let entity = match self.entity.take() {
+            Some(entity) => entity,
+            None if self.pending.is_empty() => return Ok(()),
+            None => Entity::Resource(Resource {
+                id: self.id.clone(),
+                other: mem::take(&mut self.pending),
+            }),
+        };
+
+        self.buf.clear();
+        let record = Record {
+            entity: Some(entity),
+        };
+        record.encode_length_delimited(&mut self.buf)?;
+        self.write.write_all(&self.buf)?;
+        Ok(())
+    }
+}
+
+impl<W: Output> Formatter for ProtobufFormatter<W> {
+    fn format(&mut self, triple: &Triple) -> Result<()> {
+        let subject = match triple.subject {
+            NamedOrBlankNode::NamedNode(node) => node.iri,
+            NamedOrBlankNode::BlankNode(node) => node.id,
+        };
+        if subject != self.id {
+            self.flush_record()?;
+            self.id.clear();
+            self.id.push_str(subject);
+        }
+
+        let predicate = triple.predicate.iri;
+        if self.entity.is_none() {
+            self.entity = new_entity(predicate, &self.id);
+            if let Some(entity) = &mut self.entity {
+                other(entity).append(&mut self.pending);
+            }
+        }
+        match &mut self.entity {
+            Some(entity) => {
+                if !set_field(entity, predicate, triple.object) {
+                    other(entity).push(property(triple));
+                }
+            }
+            None => self.pending.push(property(triple)),
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.flush_record()?;
+        self.write.finish()?;
+        Ok(())
+    }
+}
+
+/// The empty message of the entity of a predicate, e.g. a `Post` for
+/// `post.score`, or `None` for full IRIs such as `rdf:type`.
+fn new_entity(predicate: &str, id: &str) -> Option<Entity> {
+    if predicate.contains(':') {
+        return None;
+    }
+    let id = id.to_string();
+    let entity = match predicate.split_once('.')?.0 {
+        "badge" => Entity::Badge(Badge {
+            id,
+            ..Badge::default()
+        }),
+        "comment" => Entity::Comment(Comment {
+            id,
+            ..Comment::default()
+        }),
+        "post" => Entity::Post(Post {
+            id,
+            ..Post::default()
+        }),
+        "posthistory" => Entity::PostHistory(PostHistory {
+            id,
+            ..PostHistory::default()
+        }),
+        "postlink" => Entity::PostLink(PostLink {
+            id,
+            ..PostLink::default()
+        }),
+        "tag" => Entity::Tag(Tag {
+            id,
+            ..Tag::default()
+        }),
+        "user" => Entity::User(User {
+            id,
+            ..User::default()
+        }),
+        "vote" => Entity::Vote(Vote {
+            id,
+            ..Vote::default()
+        }),
+        "cooccurrence" => Entity::TagCooccurrence(TagCooccurrence {
+            id,
+            ..TagCooccurrence::default()
+        }),
+        "stats" => Entity::Stats(Stats {
+            id,
+            ..Stats::default()
+        }),
+        "statement" => Entity::Statement(Statement {
+            id,
+            ..Statement::default()
+        }),
+        _ => return None,
+    };
+    Some(entity)
+}
+
+/// The triples of a message that have no field of their own.
+fn other(entity: &mut Entity) -> &mut Vec<Property> {
+    match entity {
+        Entity::Badge(badge) => &mut badge.other,
+        Entity::Comment(comment) => &mut comment.other,
+        Entity::Post(post) => &mut post.other,
+        Entity::PostHistory(post_history) => &mut post_history.other,
+        Entity::PostLink(post_link) => &mut post_link.other,
+        Entity::Tag(tag) => &mut tag.other,
+        Entity::User(user) => &mut user.other,
+        Entity::Vote(vote) => &mut vote.other,
+        Entity::TagCooccurrence(cooccurrence) => &mut cooccurrence.other,
+        Entity::Stats(stats) => &mut stats.other,
+        Entity::Statement(statement) => &mut statement.other,
+        Entity::Resource(resource) => &mut resource.other,
+    }
+}
+
+/// Sets the field of a predicate. Returns `false` if the message has no such
+/// field, the field is set already, or the object does not fit its type, in
+/// which case the triple goes in `other`.
+fn set_field(entity: &mut Entity, predicate: &str, object: Term) -> bool {
+    match entity {
+        Entity::Badge(badge) => match predicate {
+            "badge.user" => node(&mut badge.user, object),
+            "badge.name" => literal(&mut badge.name, object),
+            "badge.date" => literal(&mut badge.date, object),
+            "badge.class" => literal(&mut badge.class, object),
+            "badge.tag_based" => literal(&mut badge.tag_based, object),
+            _ => false,
+        },
+        Entity::Comment(comment) => match predicate {
+            "comment.post" => node(&mut comment.post, object),
+            "comment.score" => literal(&mut comment.score, object),
+            "comment.text" => literal(&mut comment.text, object),
+            "comment.creation_date" => literal(&mut comment.creation_date, object),
+            "comment.creation_year" => literal(&mut comment.creation_year, object),
+            "comment.user" => node(&mut comment.user, object),
+            "comment.user_display_name" => literal(&mut comment.user_display_name, object),
+            "comment.content_license" => literal(&mut comment.content_license, object),
+            _ => false,
+        },
+        Entity::Post(post) => match predicate {
+            "post.type" => literal(&mut post.r#type, object),
+            "post.accepted_answer" => node(&mut post.accepted_answer, object),
+            "post.parent" => node(&mut post.parent, object),
+            "post.answer_rank" => literal(&mut post.answer_rank, object),
+            "post.is_accepted" => literal(&mut post.is_accepted, object),
+            "post.creation_date" => literal(&mut post.creation_date, object),
+            "post.creation_year" => literal(&mut post.creation_year, object),
+            "post.deletion_date" => literal(&mut post.deletion_date, object),
+            "post.score" => literal(&mut post.score, object),
+            "post.view_count" => literal(&mut post.view_count, object),
+            "post.view_bucket" => literal(&mut post.view_bucket, object),
+            "post.body" => literal(&mut post.body, object),
+            "post.owner" => node(&mut post.owner, object),
+            "post.owner_display_name" => literal(&mut post.owner_display_name, object),
+            "post.last_editor" => node(&mut post.last_editor, object),
+            "post.last_editor_display_name" => literal(&mut post.last_editor_display_name, object),
+            "post.last_edit_date" => literal(&mut post.last_edit_date, object),
+            "post.last_activity_date" => literal(&mut post.last_activity_date, object),
+            "post.title" => literal(&mut post.title, object),
+            "post.tags" => nodes(&mut post.tags, object),
+            "post.tag_name" => literals(&mut post.tag_name, object),
+            "post.answer_count" => literal(&mut post.answer_count, object),
+            "post.comment_count" => literal(&mut post.comment_count, object),
+            "post.favorite_count" => literal(&mut post.favorite_count, object),
+            "post.closed_date" => literal(&mut post.closed_date, object),
+            "post.community_owned_date" => literal(&mut post.community_owned_date, object),
+            "post.content_license" => literal(&mut post.content_license, object),
+            "post.content_hash" => literal(&mut post.content_hash, object),
+            "post.comment" => nodes(&mut post.comment, object),
+            "post.accepted_answer_of" => node(&mut post.accepted_answer_of, object),
+            "post.answer" => nodes(&mut post.answer, object),
+            "post.history" => nodes(&mut post.history, object),
+            "post.link" => nodes(&mut post.link, object),
+            "post.related_link" => nodes(&mut post.related_link, object),
+            "post.excerpt_of" => nodes(&mut post.excerpt_of, object),
+            "post.wiki_of" => nodes(&mut post.wiki_of, object),
+            "post.edited_by" => nodes(&mut post.edited_by, object),
+            "post.vote" => nodes(&mut post.vote, object),
+            _ => false,
+        },
+        Entity::PostHistory(post_history) => match predicate {
+            "posthistory.type" => literal(&mut post_history.r#type, object),
+            "posthistory.post" => node(&mut post_history.post, object),
+            "posthistory.revision_guid" => literal(&mut post_history.revision_guid, object),
+            "posthistory.creation_date" => literal(&mut post_history.creation_date, object),
+            "posthistory.user" => node(&mut post_history.user, object),
+            "posthistory.user_display_name" => literal(&mut post_history.user_display_name, object),
+            "posthistory.comment" => literal(&mut post_history.comment, object),
+            "posthistory.text" => literal(&mut post_history.text, object),
+            "posthistory.content_license" => literal(&mut post_history.content_license, object),
+            _ => false,
+        },
+        Entity::PostLink(post_link) => match predicate {
+            "postlink.creation_date" => literal(&mut post_link.creation_date, object),
+            "postlink.post" => node(&mut post_link.post, object),
+            "postlink.related_post" => node(&mut post_link.related_post, object),
+            "postlink.link_type" => literal(&mut post_link.link_type, object),
+            "postlink.link_type_id" => literal(&mut post_link.link_type_id, object),
+            _ => false,
+        },
+        Entity::Tag(tag) => match predicate {
+            "tag.name" => literal(&mut tag.name, object),
+            "tag.count" => literal(&mut tag.count, object),
+            "tag.excerpt_post" => node(&mut tag.excerpt_post, object),
+            "tag.wiki_post" => node(&mut tag.wiki_post, object),
+            "tag.cooccurs_with" => nodes(&mut tag.cooccurs_with, object),
+            "tag.post_count" => literal(&mut tag.post_count, object),
+            "tag.post" => nodes(&mut tag.post, object),
+            "tag.cooccurrence" => nodes(&mut tag.cooccurrence, object),
+            _ => false,
+        },
+        Entity::User(user) => match predicate {
+            "user.reputation" => literal(&mut user.reputation, object),
+            "user.creation_date" => literal(&mut user.creation_date, object),
+            "user.creation_year" => literal(&mut user.creation_year, object),
+            "user.display_name" => literal(&mut user.display_name, object),
+            "user.last_access_date" => literal(&mut user.last_access_date, object),
+            "user.website_url" => url(&mut user.website_url, object),
+            "user.location" => literal(&mut user.location, object),
+            "user.about_me" => literal(&mut user.about_me, object),
+            "user.about_me_text" => literal(&mut user.about_me_text, object),
+            "user.views" => literal(&mut user.views, object),
+            "user.upvotes" => literal(&mut user.upvotes, object),
+            "user.downvotes" => literal(&mut user.downvotes, object),
+            "user.profile_image_url" => url(&mut user.profile_image_url, object),
+            "user.account_id" => literal(&mut user.account_id, object),
+            "user.edited" => nodes(&mut user.edited, object),
+            "user.badge" => nodes(&mut user.badge, object),
+            "user.post_count" => literal(&mut user.post_count, object),
+            "user.comment" => nodes(&mut user.comment, object),
+            "user.post" => nodes(&mut user.post, object),
+            "user.last_edited_post" => nodes(&mut user.last_edited_post, object),
+            "user.post_history" => nodes(&mut user.post_history, object),
+            "user.vote" => nodes(&mut user.vote, object),
+            _ => false,
+        },
+        Entity::Vote(vote) => match predicate {
+            "vote.post" => node(&mut vote.post, object),
+            "vote.type" => literal(&mut vote.r#type, object),
+            "vote.user" => node(&mut vote.user, object),
+            "vote.bounty_amount" => literal(&mut vote.bounty_amount, object),
+            "vote.creation_date" => literal(&mut vote.creation_date, object),
+            _ => false,
+        },
+        Entity::TagCooccurrence(cooccurrence) => match predicate {
+            "cooccurrence.tag" => nodes(&mut cooccurrence.tag, object),
+            "cooccurrence.weight" => literal(&mut cooccurrence.weight, object),
+            _ => false,
+        },
+        Entity::Stats(stats) => match predicate {
+            "stats.badges" => literal(&mut stats.badges, object),
+            "stats.comments" => literal(&mut stats.comments, object),
+            "stats.posts" => literal(&mut stats.posts, object),
+            "stats.post_history" => literal(&mut stats.post_history, object),
+            "stats.post_links" => literal(&mut stats.post_links, object),
+            "stats.tags" => literal(&mut stats.tags, object),
+            "stats.users" => literal(&mut stats.users, object),
+            "stats.votes" => literal(&mut stats.votes, object),
+            _ => false,
+        },
+        Entity::Statement(statement) => match predicate {
+            "statement.source" => literal(&mut statement.source, object),
+            "statement.record" => literal(&mut statement.record, object),
+            _ => false,
+        },
+        Entity::Resource(_) => false,
+    }
+}
+
+/// Sets a field from the value of a literal, e.g. an `int64` from
+/// `"15"^^xsd:integer`.
+fn literal<T: FromStr>(field: &mut Option<T>, object: Term) -> bool {
+    match (&field, object) {
+        (None, Term::Literal(literal)) => match literal_value(literal).parse() {
+            Ok(value) => {
+                *field = Some(value);
+                true
+            }
+            Err(_) => false,
+        },
+        _ => false,
+    }
+}
+
+fn literals(field: &mut Vec<String>, object: Term) -> bool {
+    match object {
+        Term::Literal(literal) => {
+            field.push(literal_value(literal).to_string());
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Sets a field to the id of a node, e.g. "u3", or its IRI with `--base-iri`.
+fn node(field: &mut Option<String>, object: Term) -> bool {
+    match (&field, node_id(object)) {
+        (None, Some(id)) => {
+            *field = Some(id.to_string());
+            true
+        }
+        _ => false,
+    }
+}
+
+fn nodes(field: &mut Vec<String>, object: Term) -> bool {
+    match node_id(object) {
+        Some(id) => {
+            field.push(id.to_string());
+            true
+        }
+        None => false,
+    }
+}
+
+/// Sets a field to a URL, which is a literal or an IRI with `--urls-as-iris`.
+fn url(field: &mut Option<String>, object: Term) -> bool {
+    match (&field, object) {
+        (None, Term::NamedNode(node)) => {
+            *field = Some(node.iri.to_string());
+            true
+        }
+        _ => literal(field, object),
+    }
+}
+
+fn node_id(object: Term<'_>) -> Option<&str> {
+    match object {
+        Term::NamedNode(node) => Some(node.iri),
+        Term::BlankNode(node) => Some(node.id),
+        Term::Literal(_) => None,
+    }
+}
+
+fn property(triple: &Triple) -> Property {
+    let object = match triple.object {
+        Term::NamedNode(node) => Object::Node(node.iri.to_string()),
+        Term::BlankNode(node) => Object::Node(node.id.to_string()),
+        Term::Literal(literal) => Object::Literal(literal_value(literal).to_string()),
+    };
+    Property {
+        predicate: triple.predicate.iri.to_string(),
+        object: Some(object),
+    }
+}
+
+fn literal_value(literal: Literal<'_>) -> &str {
+    match literal {
+        Literal::Simple { value }
+        | Literal::LanguageTaggedString { value, .. }
+        | Literal::Typed { value, .. } => value,
+    }
+}
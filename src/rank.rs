@@ -0,0 +1,92 @@
+use crate::input::for_each_row;
+use crate::Opts;
+
+use anyhow::{Context, Result};
+use log::info;
+
+use std::collections::{HashMap, HashSet};
+
+/// Rank of every answer among the answers to its question, and which answers
+/// are accepted, for `--answer-rank`.
+pub struct AnswerRanks {
+    ranks: HashMap<u64, u32>,
+    accepted: HashSet<u64>,
+}
+
+impl AnswerRanks {
+    /// The 1-based rank of an answer, by descending score. Ties go to the
+    /// lower id, i.e. the earlier answer, since ids are assigned in order.
+    pub fn rank(&self, answer_id: u64) -> Option<u32> {
+        self.ranks.get(&answer_id).copied()
+    }
+
+    pub fn is_accepted(&self, answer_id: u64) -> bool {
+        self.accepted.contains(&answer_id)
+    }
+}
+
+/// Collects the answers of every question in a pass over Posts.xml, and ranks
+/// them.
+pub fn collect_answer_ranks(opts: &Opts) -> Result<AnswerRanks> {
+    info!("Posts: ranking answers");
+    let mut answers = HashMap::<u64, Vec<(i64, u64)>>::new();
+    let mut accepted = HashSet::new();
+
+    for_each_row(opts.read_options(), opts.input_path("Posts"), |row| {
+        let mut id = None;
+        let mut type_ = None;
+        let mut parent_id = None;
+        let mut accepted_answer_id = None;
+        let mut score = None;
+        for attribute in row.attributes() {
+            let (key, value) = attribute?;
+            match key {
+                b"Id" => &mut id,
+                b"PostTypeId" => &mut type_,
+                b"ParentId" => &mut parent_id,
+                b"AcceptedAnswerId" => &mut accepted_answer_id,
+                b"Score" => &mut score,
+                _ => continue,
+            }
+            .replace(value);
+        }
+
+        if let Some(accepted_answer_id) = accepted_answer_id {
+            let accepted_answer_id = accepted_answer_id
+                .parse()
+                .context("invalid `AcceptedAnswerId` in attributes")?;
+            accepted.insert(accepted_answer_id);
+        }
+
+        if type_.as_deref() != Some("2") {
+            return Ok(());
+        }
+        let (id, parent_id) = match (id, parent_id) {
+            (Some(id), Some(parent_id)) => (id, parent_id),
+            _ => return Ok(()),
+        };
+        let id = id.parse().context("invalid `Id` in attributes")?;
+        let parent_id = parent_id
+            .parse()
+            .context("invalid `ParentId` in attributes")?;
+        let score = match score {
+            Some(score) => score.parse().context("invalid `Score` in attributes")?,
+            None => 0,
+        };
+        answers.entry(parent_id).or_default().push((score, id));
+        Ok(())
+    })?;
+
+    let mut ranks = HashMap::new();
+    for (_, mut answers) in answers {
+        answers.sort_unstable_by(|(a_score, a_id), (b_score, b_id)| {
+            b_score.cmp(a_score).then(a_id.cmp(b_id))
+        });
+        for (rank, (_, id)) in answers.into_iter().enumerate() {
+            ranks.insert(id, rank as u32 + 1);
+        }
+    }
+
+    info!("Posts: ranked answers: {}", ranks.len());
+    Ok(AnswerRanks { ranks, accepted })
+}
@@ -0,0 +1,54 @@
+use crate::formatter::{absolute_iri, Formatter};
+use crate::output::Output;
+
+use anyhow::Result;
+use rio_api::formatter::TriplesFormatter;
+use rio_api::model::{NamedNode, Term, Triple};
+use rio_xml::RdfXmlFormatter;
+
+/// Writes RDF/XML, with predicates moved into `namespace`, since every
+/// predicate must be an absolute IRI that can be written as an XML element
+/// name: `post.score` becomes
+/// `<post.score xmlns="https://stackoverflow.com/schema#">`. Blank nodes are
+/// written as `rdf:nodeID`s, so ids such as `p123` are kept.
+pub struct RdfXml<W: Output> {
+    inner: RdfXmlFormatter<W>,
+    namespace: String,
+    predicate: String,
+    object: String,
+}
+
+impl<W: Output> RdfXml<W> {
+    pub fn new(write: W, namespace: &str) -> Result<Self> {
+        Ok(RdfXml {
+            inner: RdfXmlFormatter::with_indentation(write, 2)?,
+            namespace: namespace.to_string(),
+            predicate: String::new(),
+            object: String::new(),
+        })
+    }
+}
+
+impl<W: Output> Formatter for RdfXml<W> {
+    fn format(&mut self, triple: &Triple) -> Result<()> {
+        let predicate = absolute_iri(&self.namespace, triple.predicate.iri, &mut self.predicate);
+        let object = match triple.object {
+            Term::NamedNode(node) => NamedNode {
+                iri: absolute_iri(&self.namespace, node.iri, &mut self.object),
+            }
+            .into(),
+            object => object,
+        };
+        self.inner.format(&Triple {
+            subject: triple.subject,
+            predicate: NamedNode { iri: predicate },
+            object,
+        })?;
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.inner.finish()?.finish()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+use crate::formatter::Formatter;
+use crate::input::{for_each_tracked_row, OwnedRow, ReadOptions, Row};
+use crate::State;
+
+use anyhow::{anyhow, Result};
+use indicatif::ProgressBar;
+use rio_api::model::{BlankNode, Literal, NamedNode, NamedOrBlankNode, Term, Triple};
+
+use std::mem;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::Mutex;
+use std::thread;
+
+/// Number of records that are converted together by a thread. Records are
+/// small, so this keeps the threads busy without holding much in memory.
+const CHUNK_ROWS: usize = 1024;
+
+/// A chunk of records, with where to send them once they are converted.
+type Work = (Vec<OwnedRow>, SyncSender<Vec<Converted>>);
+
+/// A record that was converted on a thread of `--parallel records`, with the
+/// triples it wrote and whether it failed.
+pub struct Converted {
+    pub position: u64,
+    pub triples: TripleBuffer,
+    pub result: Result<()>,
+}
+
+/// Converts the records of an input on a thread per state in `workers`, for
+/// `--parallel records`. A reader thread splits the input into chunks of
+/// records, every worker converts whole chunks with `convert` into buffers,
+/// and the buffers are passed to `write` on this thread in the order of the
+/// input, so that the output is the same as that of a sequential run.
+///
+/// Returns the number of malformed records that were skipped by the reader,
+/// along with the states of the workers. An error of `write` stops the run.
+pub fn convert_in_parallel<'a>(
+    read: ReadOptions,
+    path: &Path,
+    bar: Option<&ProgressBar>,
+    workers: Vec<State<'a>>,
+    convert: impl Fn(&mut State<'a>, &mut dyn Formatter, &Row) -> Result<()> + Sync,
+    mut write: impl FnMut(Converted) -> Result<()>,
+) -> Result<(u64, Vec<State<'a>>)> {
+    let jobs = workers.len().max(1);
+    let (work_tx, work_rx) = mpsc::sync_channel::<Work>(jobs);
+    let work_rx = Mutex::new(work_rx);
+    // The chunks are written in the order in which they were read, which is
+    // the order of their receivers here.
+    let (order_tx, order_rx) = mpsc::sync_channel::<Receiver<Vec<Converted>>>(jobs * 2);
+
+    thread::scope(|scope| {
+        let (work_rx, convert) = (&work_rx, &convert);
+        let handles = workers
+            .into_iter()
+            .map(|mut state| {
+                scope.spawn(move || loop {
+                    let next = work_rx.lock().unwrap().recv();
+                    let (rows, done) = match next {
+                        Ok(work) => work,
+                        Err(_) => return state,
+                    };
+                    let converted = rows
+                        .iter()
+                        .map(|row| {
+                            let row = row.row();
+                            let mut triples = TripleBuffer::default();
+                            let result = convert(&mut state, &mut triples, &row);
+                            Converted {
+                                position: row.position(),
+                                triples,
+                                result,
+                            }
+                        })
+                        .collect();
+                    // The writer is gone if it failed, and the run is stopping.
+                    let _ = done.send(converted);
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let reader = scope.spawn(move || {
+            let send = |rows: Vec<OwnedRow>| {
+                let (done_tx, done_rx) = mpsc::sync_channel(1);
+                let stopped = || anyhow!("writing was stopped");
+                order_tx.send(done_rx).map_err(|_| stopped())?;
+                work_tx.send((rows, done_tx)).map_err(|_| stopped())
+            };
+            let mut rows = Vec::with_capacity(CHUNK_ROWS);
+            let malformed = for_each_tracked_row(read, path, bar, |row| {
+                rows.push(row.to_owned());
+                if rows.len() == CHUNK_ROWS {
+                    send(mem::replace(&mut rows, Vec::with_capacity(CHUNK_ROWS)))?;
+                }
+                Ok(())
+            })?;
+            if !rows.is_empty() {
+                send(rows)?;
+            }
+            Ok::<_, anyhow::Error>(malformed)
+        });
+
+        // Dropping the receivers on an error stops the reader, and with it
+        // the workers.
+        let mut written = Ok(());
+        'chunks: for done in order_rx {
+            let converted = done.recv().expect("record thread panicked");
+            for record in converted {
+                written = write(record);
+                if written.is_err() {
+                    break 'chunks;
+                }
+            }
+        }
+
+        let read = reader.join().expect("reader thread panicked");
+        let workers = handles
+            .into_iter()
+            .map(|handle| handle.join().expect("record thread panicked"))
+            .collect();
+        written?;
+        Ok((read?, workers))
+    })
+}
+
+/// Keeps the triples of a record, to be written later by `replay`.
+#[derive(Default)]
+pub struct TripleBuffer {
+    triples: Vec<OwnedTriple>,
+}
+
+struct OwnedTriple {
+    subject: OwnedNode,
+    predicate: String,
+    object: OwnedTerm,
+}
+
+enum OwnedNode {
+    NamedNode(String),
+    BlankNode(String),
+}
+
+enum OwnedTerm {
+    Node(OwnedNode),
+    Simple(String),
+    LanguageTagged(String, String),
+    Typed(String, String),
+}
+
+impl OwnedNode {
+    fn new(node: NamedOrBlankNode) -> Self {
+        match node {
+            NamedOrBlankNode::NamedNode(node) => OwnedNode::NamedNode(node.iri.to_string()),
+            NamedOrBlankNode::BlankNode(node) => OwnedNode::BlankNode(node.id.to_string()),
+        }
+    }
+
+    fn node(&self) -> NamedOrBlankNode<'_> {
+        match self {
+            OwnedNode::NamedNode(iri) => NamedNode { iri }.into(),
+            OwnedNode::BlankNode(id) => BlankNode { id }.into(),
+        }
+    }
+}
+
+impl OwnedTerm {
+    fn new(term: Term) -> Self {
+        match term {
+            Term::NamedNode(node) => OwnedTerm::Node(OwnedNode::new(node.into())),
+            Term::BlankNode(node) => OwnedTerm::Node(OwnedNode::new(node.into())),
+            Term::Literal(Literal::Simple { value }) => OwnedTerm::Simple(value.to_string()),
+            Term::Literal(Literal::LanguageTaggedString { value, language }) => {
+                OwnedTerm::LanguageTagged(value.to_string(), language.to_string())
+            }
+            Term::Literal(Literal::Typed { value, datatype }) => {
+                OwnedTerm::Typed(value.to_string(), datatype.iri.to_string())
+            }
+        }
+    }
+
+    fn term(&self) -> Term<'_> {
+        match self {
+            OwnedTerm::Node(node) => node.node().into(),
+            OwnedTerm::Simple(value) => Literal::Simple { value }.into(),
+            OwnedTerm::LanguageTagged(value, language) => {
+                Literal::LanguageTaggedString { value, language }.into()
+            }
+            OwnedTerm::Typed(value, datatype) => Literal::Typed {
+                value,
+                datatype: NamedNode { iri: datatype },
+            }
+            .into(),
+        }
+    }
+}
+
+impl TripleBuffer {
+    /// Writes the triples that were kept, in the order they came in.
+    pub fn replay(&self, formatter: &mut dyn Formatter) -> Result<()> {
+        for triple in &self.triples {
+            formatter.format(&Triple {
+                subject: triple.subject.node(),
+                predicate: NamedNode {
+                    iri: &triple.predicate,
+                },
+                object: triple.object.term(),
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl Formatter for TripleBuffer {
+    fn format(&mut self, triple: &Triple) -> Result<()> {
+        self.triples.push(OwnedTriple {
+            subject: OwnedNode::new(triple.subject),
+            predicate: triple.predicate.iri.to_string(),
+            object: OwnedTerm::new(triple.object),
+        });
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use crate::formatter::Formatter;
+use crate::types::RDF_TYPE;
+
+use anyhow::Result;
+use rio_api::model::{BlankNode, Literal, NamedNode, NamedOrBlankNode, Term, Triple};
+use xxhash_rust::xxh3::xxh3_64;
+
+use std::collections::HashSet;
+
+const RDF_STATEMENT: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#Statement";
+const RDF_SUBJECT: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#subject";
+const RDF_PREDICATE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#predicate";
+const RDF_OBJECT: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#object";
+
+/// The predicates selected with `--reify`, and the file whose triples are
+/// being reified.
+pub struct Reify {
+    source: String,
+    /// The prefix of the ids of the statements.
+    prefix: String,
+    /// `None` reifies every predicate.
+    predicates: Option<HashSet<String>>,
+}
+
+impl Reify {
+    pub fn new(source: String, predicates: &[String], prefix: String) -> Self {
+        let predicates = if predicates.iter().any(|predicate| predicate == "all") {
+            None
+        } else {
+            Some(predicates.iter().cloned().collect())
+        };
+        Reify {
+            source,
+            prefix,
+            predicates,
+        }
+    }
+
+    pub fn formatter<'a>(&'a self, inner: &'a mut dyn Formatter) -> Reifier<'a> {
+        Reifier { inner, reify: self }
+    }
+}
+
+/// Writes every triple, and for the selected predicates also an
+/// `rdf:Statement` describing it, along with the file and record it came from.
+///
+/// Statement nodes are named after a hash of the triple, so they are stable
+/// across runs and identical triples share a statement.
+pub struct Reifier<'a> {
+    inner: &'a mut dyn Formatter,
+    reify: &'a Reify,
+}
+
+impl Formatter for Reifier<'_> {
+    fn format(&mut self, triple: &Triple) -> Result<()> {
+        self.inner.format(triple)?;
+
+        if let Some(predicates) = &self.reify.predicates {
+            if !predicates.contains(triple.predicate.iri) {
+                return Ok(());
+            }
+        }
+
+        let record = match triple.subject {
+            NamedOrBlankNode::NamedNode(node) => node.iri,
+            NamedOrBlankNode::BlankNode(node) => node.id,
+        };
+        let hash = xxh3_64(triple.to_string().as_bytes());
+        let statement = format!("{}{:016x}", self.reify.prefix, hash);
+        let subject = BlankNode { id: &statement }.into();
+
+        let properties: [(&str, Term); 6] = [
+            (RDF_TYPE, NamedNode { iri: RDF_STATEMENT }.into()),
+            (RDF_SUBJECT, triple.subject.into()),
+            (RDF_PREDICATE, triple.predicate.into()),
+            (RDF_OBJECT, triple.object),
+            (
+                "statement.source",
+                Literal::Simple {
+                    value: &self.reify.source,
+                }
+                .into(),
+            ),
+            ("statement.record", Literal::Simple { value: record }.into()),
+        ];
+        for &(iri, object) in &properties {
+            self.inner.format(&Triple {
+                subject,
+                predicate: NamedNode { iri },
+                object,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// The inner formatter is borrowed, so it is finished by its owner.
+    fn finish(self: Box<Self>) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{EntityCount, WRITERS};
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Map, Value};
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The entities that were written completely, for `--resume`. The checkpoint is
+/// a JSON file next to the output, which is rewritten whenever an entity is
+/// finished, with the counts of the entity so that the summary of a resumed
+/// run stays complete.
+pub struct Checkpoint {
+    path: PathBuf,
+    args: Vec<String>,
+    finished: Mutex<Map<String, Value>>,
+}
+
+impl Checkpoint {
+    /// Reads the checkpoint at `path`, or starts a new one if there is none.
+    /// A checkpoint that was written with other arguments is rejected, since
+    /// its entities may not match the rest of the output.
+    pub fn open(path: PathBuf) -> Result<Self> {
+        let args = env::args().skip(1).collect::<Vec<_>>();
+        let finished = match fs::read(&path) {
+            Ok(bytes) => {
+                let checkpoint: Value = serde_json::from_slice(&bytes)
+                    .with_context(|| format!("could not parse checkpoint {}", path.display()))?;
+                if checkpoint["args"] != json!(args) {
+                    bail!(
+                        "checkpoint {} was written by a run with other arguments, delete it to \
+                         start over",
+                        path.display()
+                    );
+                }
+                match checkpoint["finished"].as_object() {
+                    Some(finished) => finished.clone(),
+                    None => bail!("checkpoint {} has no finished entities", path.display()),
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Map::new(),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("could not read checkpoint {}", path.display()));
+            }
+        };
+        Ok(Checkpoint {
+            path,
+            args,
+            finished: Mutex::new(finished),
+        })
+    }
+
+    /// The counts of an entity that was finished by an earlier run, if any.
+    pub fn finished(&self, entity: &str) -> Option<Vec<EntityCount>> {
+        let finished = self.finished.lock().unwrap();
+        let counts = finished.get(entity)?.as_array()?;
+        Some(
+            counts
+                .iter()
+                .filter_map(|count| {
+                    Some(EntityCount {
+                        name: static_name(count["name"].as_str()?)?,
+                        records: count["records"].as_u64()?,
+                        skipped: count["skipped"].as_u64()?,
+                        triples: count["triples"].as_u64()?,
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    /// Records that an entity was written completely, along with its counts.
+    pub fn finish(&self, entity: &str, counts: &[EntityCount]) -> Result<()> {
+        let counts = counts
+            .iter()
+            .map(|count| {
+                json!({
+                    "name": count.name,
+                    "records": count.records,
+                    "skipped": count.skipped,
+                    "triples": count.triples,
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut finished = self.finished.lock().unwrap();
+        finished.insert(entity.to_string(), Value::from(counts));
+        self.write(&finished)
+    }
+
+    /// Deletes the checkpoint once the output is complete.
+    pub fn remove(self) -> Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e)
+                .with_context(|| format!("could not delete checkpoint {}", self.path.display())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes the checkpoint to a temporary file first, so that a run that is
+    /// interrupted while writing it leaves the previous one intact.
+    fn write(&self, finished: &Map<String, Value>) -> Result<()> {
+        let checkpoint = json!({ "args": self.args, "finished": finished });
+        let tmp = with_suffix(&self.path, ".tmp");
+        let file = File::create(&tmp).context("could not create checkpoint")?;
+        let mut write = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut write, &checkpoint)
+            .context("could not write checkpoint")?;
+        write.flush().context("could not write checkpoint")?;
+        fs::rename(&tmp, &self.path).context("could not write checkpoint")
+    }
+}
+
+/// The checkpoint of an output, e.g. `output.nt.gz.resume`.
+pub fn checkpoint_path(output: &Path) -> PathBuf {
+    with_suffix(output, ".resume")
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.to_path_buf().into_os_string();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// The name of an entity or of derived data, as it is counted in `Report`.
+fn static_name(name: &str) -> Option<&'static str> {
+    WRITERS
+        .iter()
+        .map(|&(name, _)| name)
+        .chain(Some("TagCooccurrence"))
+        .find(|&known| known == name)
+}
@@ -0,0 +1,130 @@
+use crate::formatter::Formatter;
+
+use anyhow::Result;
+use rio_api::model::{Literal, Term, Triple};
+
+/// Removes the characters that XML 1.0 does not allow, mostly ASCII control
+/// characters such as `\u{7}`, from every literal. N-Triples allows them, but
+/// parsers such as Jena reject them and RDF/XML cannot hold them. Disabled by
+/// `--keep-control-chars`.
+pub struct StripControlChars {
+    inner: Box<dyn Formatter + Send>,
+    buf: String,
+}
+
+impl StripControlChars {
+    pub fn new(inner: Box<dyn Formatter + Send>) -> Self {
+        StripControlChars {
+            inner,
+            buf: String::new(),
+        }
+    }
+}
+
+impl Formatter for StripControlChars {
+    fn format(&mut self, triple: &Triple) -> Result<()> {
+        let literal = match triple.object {
+            Term::Literal(literal) => literal,
+            _ => return self.inner.format(triple),
+        };
+        let value = match literal {
+            Literal::Simple { value }
+            | Literal::LanguageTaggedString { value, .. }
+            | Literal::Typed { value, .. } => value,
+        };
+        if value.chars().all(is_xml_char) {
+            return self.inner.format(triple);
+        }
+
+        self.buf.clear();
+        self.buf.extend(value.chars().filter(|&c| is_xml_char(c)));
+        let value = self.buf.as_str();
+        let literal = match literal {
+            Literal::Simple { .. } => Literal::Simple { value },
+            Literal::LanguageTaggedString { language, .. } => {
+                Literal::LanguageTaggedString { value, language }
+            }
+            Literal::Typed { datatype, .. } => Literal::Typed { value, datatype },
+        };
+        self.inner.format(&Triple {
+            subject: triple.subject,
+            predicate: triple.predicate,
+            object: literal.into(),
+        })
+    }
+
+    fn set_graph(&mut self, entity: &str) {
+        self.inner.set_graph(entity)
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.inner.finish()
+    }
+}
+
+/// Whether XML 1.0 allows the character.
+pub fn is_xml_char(c: char) -> bool {
+    matches!(
+        c,
+        '\t' | '\n' | '\r' | '\u{20}'..='\u{d7ff}' | '\u{e000}'..='\u{fffd}' | '\u{10000}'..
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rio_api::model::{BlankNode, NamedNode};
+    use rio_api::parser::TriplesParser;
+    use rio_turtle::{NTriplesParser, TurtleError};
+
+    use std::sync::{Arc, Mutex};
+
+    /// Keeps the N-Triples lines of the triples it is given.
+    struct Lines(Arc<Mutex<Vec<String>>>);
+
+    impl Formatter for Lines {
+        fn format(&mut self, triple: &Triple) -> Result<()> {
+            self.0.lock().unwrap().push(triple.to_string());
+            Ok(())
+        }
+
+        fn finish(self: Box<Self>) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn strips_control_chars_from_literals() {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let mut formatter = StripControlChars::new(Box::new(Lines(lines.clone())));
+        formatter
+            .format(&Triple {
+                subject: BlankNode { id: "p1" }.into(),
+                predicate: NamedNode {
+                    iri: "https://stackoverflow.com/schema#post.body",
+                },
+                object: Literal::Simple {
+                    value: "ring\x07 the bell\u{0}",
+                }
+                .into(),
+            })
+            .unwrap();
+
+        let lines = lines.lock().unwrap();
+        assert_eq!(
+            *lines,
+            ["_:p1 <https://stackoverflow.com/schema#post.body> \"ring the bell\" ."]
+        );
+        let mut values = Vec::new();
+        NTriplesParser::new(lines[0].as_bytes())
+            .parse_all(&mut |triple| {
+                if let Term::Literal(Literal::Simple { value }) = triple.object {
+                    values.push(value.to_string());
+                }
+                Ok(()) as Result<(), TurtleError>
+            })
+            .unwrap();
+        assert_eq!(values, ["ring the bell"]);
+    }
+}
@@ -0,0 +1,104 @@
+use crate::datatype::XSD_INTEGER;
+use crate::formatter::Formatter;
+
+use anyhow::Result;
+use rio_api::model::{BlankNode, Literal, NamedNode, NamedOrBlankNode, Term, Triple};
+
+use std::collections::HashMap;
+
+/// For every entity, the statistic that counts it and a predicate that every
+/// emitted record has exactly once.
+const ENTITY_COUNTS: &[(&str, &str)] = &[
+    ("stats.badges", "badge.name"),
+    ("stats.comments", "comment.text"),
+    ("stats.posts", "post.creation_date"),
+    ("stats.post_history", "posthistory.revision_guid"),
+    ("stats.post_links", "postlink.creation_date"),
+    ("stats.tags", "tag.name"),
+    ("stats.users", "user.creation_date"),
+    ("stats.votes", "vote.creation_date"),
+];
+
+/// Counts the triples written to it instead of writing them, and writes only
+/// the statistics when finished, for `--output-stats-only`:
+///
+/// - `_:stats` gets a `stats.<entity>` count per entity;
+/// - every tag gets a `tag.post_count` of the posts tagged with it;
+/// - every user gets a `user.post_count` of the posts they own.
+///
+/// Counting the triples rather than the input records means that the
+/// statistics describe the output that the other options would produce.
+pub struct StatsFormatter {
+    inner: Box<dyn Formatter + Send>,
+    entities: [u64; ENTITY_COUNTS.len()],
+    tag_posts: HashMap<String, u64>,
+    user_posts: HashMap<String, u64>,
+}
+
+impl StatsFormatter {
+    pub fn new(inner: Box<dyn Formatter + Send>) -> Self {
+        StatsFormatter {
+            inner,
+            entities: [0; ENTITY_COUNTS.len()],
+            tag_posts: HashMap::new(),
+            user_posts: HashMap::new(),
+        }
+    }
+}
+
+impl Formatter for StatsFormatter {
+    fn format(&mut self, triple: &Triple) -> Result<()> {
+        let predicate = triple.predicate.iri;
+        if let Some(idx) = ENTITY_COUNTS.iter().position(|&(_, p)| p == predicate) {
+            self.entities[idx] += 1;
+            return Ok(());
+        }
+
+        let counts = match predicate {
+            "post.tags" => &mut self.tag_posts,
+            "post.owner" => &mut self.user_posts,
+            _ => return Ok(()),
+        };
+        if let Term::BlankNode(node) = triple.object {
+            *counts.entry(node.id.to_string()).or_insert(0) += 1;
+        }
+        Ok(())
+    }
+
+    fn set_graph(&mut self, entity: &str) {
+        self.inner.set_graph(entity)
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.inner.set_graph("Stats");
+        for (&(iri, _), count) in ENTITY_COUNTS.iter().zip(&self.entities) {
+            if *count != 0 {
+                write_count(self.inner.as_mut(), "stats", iri, *count)?;
+            }
+        }
+        for (iri, counts) in &[
+            ("tag.post_count", &self.tag_posts),
+            ("user.post_count", &self.user_posts),
+        ] {
+            let mut counts = counts.iter().collect::<Vec<_>>();
+            counts.sort_unstable();
+            for (id, &count) in counts {
+                write_count(self.inner.as_mut(), id, iri, count)?;
+            }
+        }
+        self.inner.finish()
+    }
+}
+
+fn write_count(formatter: &mut dyn Formatter, id: &str, iri: &str, count: u64) -> Result<()> {
+    let count = count.to_string();
+    formatter.format(&Triple {
+        subject: NamedOrBlankNode::from(BlankNode { id }),
+        predicate: NamedNode { iri },
+        object: Literal::Typed {
+            value: &count,
+            datatype: NamedNode { iri: XSD_INTEGER },
+        }
+        .into(),
+    })
+}
@@ -0,0 +1,52 @@
+use anyhow::{bail, Context, Result};
+use log::warn;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// Exit code of a run that was stopped by `--timeout`, as with `timeout(1)`.
+pub const TIMEOUT_EXIT_CODE: i32 = 124;
+
+/// Set when the run should stop at the next record boundary. The read loops
+/// check it before every record, so that the output can still be finished
+/// cleanly.
+static STOP: AtomicBool = AtomicBool::new(false);
+
+pub fn requested() -> bool {
+    STOP.load(Ordering::Relaxed)
+}
+
+/// Requests a stop once `timeout` has elapsed.
+pub fn stop_after(timeout: Duration) {
+    thread::spawn(move || {
+        thread::sleep(timeout);
+        warn!(
+            "Timeout of {}s reached, stopping at the next record",
+            timeout.as_secs()
+        );
+        STOP.store(true, Ordering::Relaxed);
+    });
+}
+
+/// Parses a duration such as `90`, `90s`, `15m` or `2h`. Plain numbers are
+/// seconds.
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let (number, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(idx) => s.split_at(idx),
+        None => (s, "s"),
+    };
+    let number = number
+        .parse::<u64>()
+        .context("expected a duration such as `90s`, `15m` or `2h`")?;
+    let seconds = match unit {
+        "s" => Some(number),
+        "m" => number.checked_mul(60),
+        "h" => number.checked_mul(60 * 60),
+        _ => bail!("unknown duration unit `{}`, expected `s`, `m` or `h`", unit),
+    };
+    match seconds {
+        Some(seconds) => Ok(Duration::from_secs(seconds)),
+        None => bail!("duration too large"),
+    }
+}
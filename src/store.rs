@@ -0,0 +1,81 @@
+use crate::formatter::{absolute_iri, Formatter, PREDICATE_NAMESPACE};
+
+use anyhow::{Context, Result};
+use oxigraph::model::{
+    BlankNodeRef, GraphNameRef, LiteralRef, NamedNodeRef, QuadRef, SubjectRef, TermRef,
+};
+use oxigraph::store::Store;
+use rio_api::model::{Literal, NamedOrBlankNode, Term, Triple};
+
+use std::path::Path;
+
+/// Inserts the triples into the default graph of an Oxigraph store for
+/// `--store`, instead of serializing them. Relative predicates are moved under
+/// `--predicate-base`, or `PREDICATE_NAMESPACE` if it is empty, since a store
+/// only holds absolute IRIs. Blank nodes keep their ids, so the references
+/// between records are kept across entities.
+pub struct StoreFormatter {
+    store: Store,
+    namespace: String,
+    predicate: String,
+    object: String,
+}
+
+impl StoreFormatter {
+    /// Opens the store in `dir`, which is created if it does not exist yet.
+    pub fn open(dir: &Path, predicate_base: &str) -> Result<Self> {
+        let store =
+            Store::open(dir).with_context(|| format!("Could not open store {}", dir.display()))?;
+        let namespace = match predicate_base {
+            "" => PREDICATE_NAMESPACE,
+            base => base,
+        };
+        Ok(StoreFormatter {
+            store,
+            namespace: namespace.to_string(),
+            predicate: String::new(),
+            object: String::new(),
+        })
+    }
+}
+
+impl Formatter for StoreFormatter {
+    fn format(&mut self, triple: &Triple) -> Result<()> {
+        let subject: SubjectRef = match triple.subject {
+            NamedOrBlankNode::NamedNode(node) => NamedNodeRef::new_unchecked(node.iri).into(),
+            NamedOrBlankNode::BlankNode(node) => BlankNodeRef::new_unchecked(node.id).into(),
+        };
+        let predicate = absolute_iri(&self.namespace, triple.predicate.iri, &mut self.predicate);
+        let object: TermRef = match triple.object {
+            Term::NamedNode(node) => {
+                let iri = absolute_iri(&self.namespace, node.iri, &mut self.object);
+                NamedNodeRef::new_unchecked(iri).into()
+            }
+            Term::BlankNode(node) => BlankNodeRef::new_unchecked(node.id).into(),
+            Term::Literal(Literal::Simple { value }) => {
+                LiteralRef::new_simple_literal(value).into()
+            }
+            Term::Literal(Literal::LanguageTaggedString { value, language }) => {
+                LiteralRef::new_language_tagged_literal_unchecked(value, language).into()
+            }
+            Term::Literal(Literal::Typed { value, datatype }) => {
+                LiteralRef::new_typed_literal(value, NamedNodeRef::new_unchecked(datatype.iri))
+                    .into()
+            }
+        };
+        let quad = QuadRef::new(
+            subject,
+            NamedNodeRef::new_unchecked(predicate),
+            object,
+            GraphNameRef::DefaultGraph,
+        );
+        self.store
+            .insert(quad)
+            .context("Could not insert into store")?;
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.store.flush().context("Could not flush store")
+    }
+}
@@ -0,0 +1,119 @@
+use crate::formatter::Formatter;
+
+use anyhow::Result;
+use clap::ArgEnum;
+use rio_api::model::{NamedNode, Term, Triple};
+
+use std::collections::HashMap;
+
+#[derive(ArgEnum, Clone, Copy, PartialEq)]
+pub enum PredicateStyle {
+    /// `post.view_count`, as written by the writers.
+    Snake,
+    /// `post.viewCount`.
+    Camel,
+    /// `post.view-count`.
+    Kebab,
+}
+
+impl PredicateStyle {
+    /// Applies the style to the local part of a predicate, i.e. the part after
+    /// the entity. Full IRIs such as `rdf:type` are left as they are.
+    pub fn apply(self, iri: &str) -> String {
+        let idx = match iri.find('.') {
+            Some(idx) if !iri.contains(':') => idx + 1,
+            _ => return iri.to_string(),
+        };
+        let (entity, local) = iri.split_at(idx);
+
+        let mut styled = entity.to_string();
+        match self {
+            PredicateStyle::Snake => styled.push_str(local),
+            PredicateStyle::Camel => {
+                for (idx, word) in local.split('_').enumerate() {
+                    let mut chars = word.chars();
+                    match chars.next() {
+                        Some(first) if idx != 0 => {
+                            styled.extend(first.to_uppercase());
+                            styled.push_str(chars.as_str());
+                        }
+                        _ => styled.push_str(word),
+                    }
+                }
+            }
+            PredicateStyle::Kebab => styled.push_str(&local.replace('_', "-")),
+        }
+        styled
+    }
+}
+
+/// Renames the predicates of every triple according to `--predicate-style`.
+/// Named node objects are renamed too, since they are predicates as well
+/// (e.g. the `rdf:predicate` of a reified statement).
+pub struct StyledFormatter {
+    inner: Box<dyn Formatter + Send>,
+    style: PredicateStyle,
+    names: HashMap<String, String>,
+}
+
+impl StyledFormatter {
+    pub fn new(inner: Box<dyn Formatter + Send>, style: PredicateStyle) -> Self {
+        StyledFormatter {
+            inner,
+            style,
+            names: HashMap::new(),
+        }
+    }
+}
+
+impl Formatter for StyledFormatter {
+    fn format(&mut self, triple: &Triple) -> Result<()> {
+        // Full IRIs are not renamed, so they are not kept in `names` either:
+        // with `--urls-as-iris`, there is one for every URL.
+        for iri in [Some(triple.predicate.iri), named_object(triple)]
+            .iter()
+            .flatten()
+            .filter(|iri| !iri.contains(':'))
+        {
+            if !self.names.contains_key(*iri) {
+                self.names.insert(iri.to_string(), self.style.apply(iri));
+            }
+        }
+
+        let object = match named_object(triple) {
+            Some(iri) => NamedNode {
+                iri: styled(&self.names, iri),
+            }
+            .into(),
+            None => triple.object,
+        };
+        let triple = Triple {
+            subject: triple.subject,
+            predicate: NamedNode {
+                iri: styled(&self.names, triple.predicate.iri),
+            },
+            object,
+        };
+        self.inner.format(&triple)
+    }
+
+    fn set_graph(&mut self, entity: &str) {
+        self.inner.set_graph(entity)
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.inner.finish()
+    }
+}
+
+fn named_object<'a>(triple: &Triple<'a>) -> Option<&'a str> {
+    match triple.object {
+        Term::NamedNode(node) => Some(node.iri),
+        _ => None,
+    }
+}
+
+/// The renamed IRI, or the IRI itself if it is a full one.
+fn styled<'a>(names: &'a HashMap<String, String>, iri: &'a str) -> &'a str {
+    names.get(iri).map_or(iri, String::as_str)
+}
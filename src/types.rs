@@ -0,0 +1,65 @@
+pub const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+
+/// Classes of the `PostTypeId`s, which are subclasses of `Post`.
+pub const POST_TYPES: &[(&str, &str)] = &[
+    ("1", "Question"),
+    ("2", "Answer"),
+    ("3", "OrphanedTagWiki"),
+    ("4", "TagWikiExcerpt"),
+    ("5", "TagWiki"),
+    ("6", "ModeratorNomination"),
+    ("7", "WikiPlaceholder"),
+    ("8", "PrivilegeWiki"),
+];
+
+/// Classes of the `PostHistoryTypeId`s, which are subclasses of `PostHistory`.
+pub const POST_HISTORY_TYPES: &[(&str, &str)] = &[
+    ("1", "InitialTitle"),
+    ("2", "InitialBody"),
+    ("3", "InitialTags"),
+    ("4", "EditTitle"),
+    ("5", "EditBody"),
+    ("6", "EditTags"),
+    ("7", "RollbackTitle"),
+    ("8", "RollbackBody"),
+    ("9", "RollbackTags"),
+    ("10", "PostClosed"),
+    ("11", "PostReopened"),
+    ("12", "PostDeleted"),
+    ("13", "PostUndeleted"),
+    ("14", "PostLocked"),
+    ("15", "PostUnlocked"),
+    ("16", "CommunityOwned"),
+    ("17", "PostMigrated"),
+    ("18", "QuestionMerged"),
+    ("19", "QuestionProtected"),
+    ("20", "QuestionUnprotected"),
+    ("21", "PostDisassociated"),
+    ("22", "QuestionUnmerged"),
+    ("24", "SuggestedEditApplied"),
+    ("25", "PostTweeted"),
+    ("31", "CommentDiscussionMovedToChat"),
+    ("33", "PostNoticeAdded"),
+    ("34", "PostNoticeRemoved"),
+    ("35", "PostMigratedAway"),
+    ("36", "PostMigratedHere"),
+    ("37", "PostMergeSource"),
+    ("38", "PostMergeDestination"),
+    ("50", "BumpedByCommunityUser"),
+    ("52", "QuestionBecameHotNetworkQuestion"),
+    ("53", "QuestionRemovedFromHotNetworkQuestions"),
+    ("66", "CreatedFromAskWizard"),
+];
+
+/// Individuals of the `LinkTypeId`s, which are instances of `LinkType`.
+pub const LINK_TYPES: &[(&str, &str)] = &[("1", "LinkedLink"), ("3", "DuplicateLink")];
+
+/// The name of a type code in one of the tables above, for `--type-names` and
+/// `--link-type-names`. Names are written as IRIs under the predicate
+/// namespace, like the predicates.
+pub fn type_name(types: &'static [(&str, &str)], id: &str) -> Option<&'static str> {
+    types
+        .iter()
+        .find(|&&(type_id, _)| type_id == id)
+        .map(|&(_, name)| name)
+}
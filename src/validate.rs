@@ -0,0 +1,94 @@
+use crate::formatter::{absolute_iri, Formatter, PREDICATE_NAMESPACE};
+use crate::sanitize::is_xml_char;
+
+use anyhow::{anyhow, bail, Result};
+use oxiri::Iri;
+use rio_api::model::{Literal, NamedOrBlankNode, Term, Triple};
+
+/// Checks the triples of a record for `--validate` before passing them on,
+/// and collects what is wrong with them. Invalid triples are still written.
+/// The characters of literals are only checked if they are kept, i.e. with
+/// `--keep-control-chars`.
+pub struct Validator<'a> {
+    inner: &'a mut dyn Formatter,
+    check_chars: bool,
+    pub problems: Vec<String>,
+    buf: String,
+}
+
+impl<'a> Validator<'a> {
+    pub fn new(inner: &'a mut dyn Formatter, check_chars: bool) -> Self {
+        Validator {
+            inner,
+            check_chars,
+            problems: Vec::new(),
+            buf: String::new(),
+        }
+    }
+}
+
+impl Formatter for Validator<'_> {
+    fn format(&mut self, triple: &Triple) -> Result<()> {
+        if let Err(e) = validate_triple(triple, self.check_chars, &mut self.buf) {
+            self.problems.push(format!("{}: {}", e, triple));
+        }
+        self.inner.format(triple)
+    }
+
+    /// The inner formatter is borrowed, so it is finished by its owner.
+    fn finish(self: Box<Self>) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Checks that every IRI is valid, relative ones such as predicates once they
+/// are resolved against the default namespace, that blank node ids are
+/// well-formed, and with `check_chars` that literals only contain characters
+/// that XML 1.0 allows.
+fn validate_triple(triple: &Triple, check_chars: bool, buf: &mut String) -> Result<()> {
+    match triple.subject {
+        NamedOrBlankNode::NamedNode(node) => validate_iri(node.iri, buf)?,
+        NamedOrBlankNode::BlankNode(node) => validate_blank_node(node.id)?,
+    }
+    validate_iri(triple.predicate.iri, buf)?;
+    let value = match triple.object {
+        Term::NamedNode(node) => return validate_iri(node.iri, buf),
+        Term::BlankNode(node) => return validate_blank_node(node.id),
+        Term::Literal(Literal::Simple { value }) => value,
+        Term::Literal(Literal::LanguageTaggedString { value, .. }) => value,
+        Term::Literal(Literal::Typed { value, datatype }) => {
+            validate_iri(datatype.iri, buf)?;
+            value
+        }
+    };
+    if check_chars {
+        validate_chars(value)?;
+    }
+    Ok(())
+}
+
+fn validate_iri(iri: &str, buf: &mut String) -> Result<()> {
+    let absolute = absolute_iri(PREDICATE_NAMESPACE, iri, buf);
+    Iri::parse(absolute).map_err(|e| anyhow!("invalid IRI <{}>: {}", iri, e))?;
+    Ok(())
+}
+
+/// Blank node labels as in N-Triples, roughly: letters, digits, `_`, `-` and
+/// `.`, starting with a letter, digit or `_` and not ending with `.`.
+fn validate_blank_node(id: &str) -> Result<()> {
+    let valid = id
+        .chars()
+        .enumerate()
+        .all(|(idx, c)| c.is_alphanumeric() || c == '_' || (idx > 0 && (c == '-' || c == '.')));
+    if id.is_empty() || !valid || id.ends_with('.') {
+        bail!("invalid blank node id {:?}", id);
+    }
+    Ok(())
+}
+
+fn validate_chars(value: &str) -> Result<()> {
+    if let Some((idx, c)) = value.char_indices().find(|&(_, c)| !is_xml_char(c)) {
+        bail!("literal has the character {:?} at byte {}", c, idx);
+    }
+    Ok(())
+}
@@ -0,0 +1,168 @@
+use crate::formatter::{absolute_iri, Formatter, PREDICATE_NAMESPACE};
+use crate::{Opts, Report};
+
+use anyhow::{Context, Result};
+use rio_api::model::Triple;
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// The class of the records of every entity.
+const CLASSES: &[(&str, &str)] = &[
+    ("Badges", "Badge"),
+    ("Comments", "Comment"),
+    ("Posts", "Post"),
+    ("PostHistory", "PostHistory"),
+    ("PostLinks", "PostLink"),
+    ("Tags", "Tag"),
+    ("Users", "User"),
+    ("Votes", "Vote"),
+];
+
+/// The triples and distinct predicates that were written, for `--emit-void`
+/// and `--emit-predicates`.
+#[derive(Default)]
+pub struct OutputStats {
+    pub triples: u64,
+    pub predicates: HashSet<String>,
+}
+
+impl OutputStats {
+    pub fn merge(&mut self, other: OutputStats) {
+        self.triples += other.triples;
+        self.predicates.extend(other.predicates);
+    }
+}
+
+/// Counts the triples and predicates that are written to the output. It sits
+/// right above the output, so that it also sees the triples of `--reify` and
+/// `--emit-inverse`, which the per-entity counts leave out.
+pub struct OutputCounter<'a> {
+    inner: &'a mut dyn Formatter,
+    stats: &'a mut OutputStats,
+}
+
+impl<'a> OutputCounter<'a> {
+    pub fn new(inner: &'a mut dyn Formatter, stats: &'a mut OutputStats) -> Self {
+        OutputCounter { inner, stats }
+    }
+}
+
+impl Formatter for OutputCounter<'_> {
+    fn format(&mut self, triple: &Triple) -> Result<()> {
+        self.stats.triples += 1;
+        if !self.stats.predicates.contains(triple.predicate.iri) {
+            self.stats
+                .predicates
+                .insert(triple.predicate.iri.to_string());
+        }
+        self.inner.format(triple)
+    }
+
+    /// The inner formatter is borrowed, so it is finished by its owner.
+    fn finish(self: Box<Self>) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes a VoID description of the output in Turtle for `--emit-void`: the
+/// number of triples including derived data, the number of distinct
+/// predicates, a class partition with the number of records converted for
+/// every entity that was read, and the dump it was converted from.
+pub fn write_void(path: &Path, opts: &Opts, report: &Report) -> Result<()> {
+    let namespace = match opts.predicate_base.as_str() {
+        "" => PREDICATE_NAMESPACE,
+        base => base,
+    };
+    let partitions = CLASSES
+        .iter()
+        .filter(|&&(name, _)| report.records(name) != 0)
+        .map(|&(name, class)| (class, report.records(name) - report.skipped(name)))
+        .collect::<Vec<_>>();
+
+    let file =
+        File::create(path).with_context(|| format!("could not create {}", path.display()))?;
+    let mut write = BufWriter::new(file);
+
+    writeln!(write, "@prefix dcterms: <http://purl.org/dc/terms/> .")?;
+    writeln!(write, "@prefix void: <http://rdfs.org/ns/void#> .")?;
+    writeln!(write)?;
+    writeln!(write, "[] a void:Dataset ;")?;
+    writeln!(
+        write,
+        "    dcterms:source {} ;",
+        turtle_string(&opts.path_from.to_string_lossy())
+    )?;
+    writeln!(write, "    void:vocabulary <{}> ;", namespace)?;
+    writeln!(write, "    void:triples {} ;", report.output_stats.triples)?;
+    write!(
+        write,
+        "    void:properties {}",
+        report.output_stats.predicates.len()
+    )?;
+    for (i, (class, entities)) in partitions.iter().enumerate() {
+        let separator = if i == 0 {
+            " ;\n    void:classPartition"
+        } else {
+            ","
+        };
+        writeln!(write, "{} [", separator)?;
+        writeln!(write, "        void:class <{}{}> ;", namespace, class)?;
+        write!(write, "        void:entities {}\n    ]", entities)?;
+    }
+    writeln!(write, " .")?;
+
+    write
+        .flush()
+        .with_context(|| format!("could not write {}", path.display()))
+}
+
+/// Writes the predicates that were written for `--emit-predicates`, one per
+/// line in alphabetical order, with their style and base applied as in the
+/// output, e.g. `https://stackoverflow.com/schema#post.score`.
+pub fn write_predicates(path: &Path, opts: &Opts, report: &Report) -> Result<()> {
+    let mut buf = String::new();
+    let mut predicates = report
+        .output_stats
+        .predicates
+        .iter()
+        .map(|predicate| {
+            let styled = opts.predicate_style.apply(predicate);
+            match opts.predicate_base.as_str() {
+                "" => styled,
+                base => absolute_iri(base, &styled, &mut buf).to_string(),
+            }
+        })
+        .collect::<Vec<_>>();
+    predicates.sort_unstable();
+
+    let file =
+        File::create(path).with_context(|| format!("could not create {}", path.display()))?;
+    let mut write = BufWriter::new(file);
+    for predicate in predicates {
+        writeln!(write, "{}", predicate)?;
+    }
+    write
+        .flush()
+        .with_context(|| format!("could not write {}", path.display()))
+}
+
+/// A Turtle string literal, with the quotes, backslashes and line breaks of
+/// `value` escaped.
+fn turtle_string(value: &str) -> String {
+    let mut literal = String::with_capacity(value.len() + 2);
+    literal.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
@@ -0,0 +1,128 @@
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde_json::{Map, Value};
+
+use std::fmt::Display;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// How many warnings of each category are logged individually before the rest
+/// are only counted.
+const MAX_LOGGED: u64 = 10;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    MissingInput,
+    PrunedTagPairs,
+    DanglingReference,
+    InvalidDate,
+    InvalidInteger,
+    InvalidBoolean,
+    UnknownType,
+    MalformedInput,
+    InvalidTriple,
+    UnknownAttribute,
+    InvalidUrl,
+    InvalidTags,
+}
+
+impl Category {
+    const ALL: &'static [Category] = &[
+        Category::MissingInput,
+        Category::PrunedTagPairs,
+        Category::DanglingReference,
+        Category::InvalidDate,
+        Category::InvalidInteger,
+        Category::InvalidBoolean,
+        Category::UnknownType,
+        Category::MalformedInput,
+        Category::InvalidTriple,
+        Category::UnknownAttribute,
+        Category::InvalidUrl,
+        Category::InvalidTags,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Category::MissingInput => "missing_input",
+            Category::PrunedTagPairs => "pruned_tag_pairs",
+            Category::DanglingReference => "dangling_reference",
+            Category::InvalidDate => "invalid_date",
+            Category::InvalidInteger => "invalid_integer",
+            Category::InvalidBoolean => "invalid_boolean",
+            Category::UnknownType => "unknown_type",
+            Category::MalformedInput => "malformed_input",
+            Category::InvalidTriple => "invalid_triple",
+            Category::UnknownAttribute => "unknown_attribute",
+            Category::InvalidUrl => "invalid_url",
+            Category::InvalidTags => "invalid_tags",
+        }
+    }
+}
+
+/// Collects data quality warnings by category, so that they can be summarized
+/// at the end of a run instead of getting lost in the log.
+#[derive(Default)]
+pub struct Warnings {
+    counts: [u64; Category::ALL.len()],
+}
+
+impl Warnings {
+    pub fn warn(&mut self, category: Category, message: impl Display) {
+        let count = &mut self.counts[category as usize];
+        *count += 1;
+
+        if *count <= MAX_LOGGED {
+            warn!("{}", message);
+        }
+        if *count == MAX_LOGGED {
+            warn!("{}: further warnings will only be counted", category.name());
+        }
+    }
+
+    /// Counts warnings that were already logged elsewhere.
+    pub fn add(&mut self, category: Category, count: u64) {
+        self.counts[category as usize] += count;
+    }
+
+    pub fn count(&self, category: Category) -> u64 {
+        self.counts[category as usize]
+    }
+
+    /// Adds the counts of `other`, e.g. of a writer that ran on another thread.
+    pub fn merge(&mut self, other: &Warnings) {
+        for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other;
+        }
+    }
+
+    pub fn log_summary(&self) {
+        if self.counts.iter().all(|&count| count == 0) {
+            info!("Warnings: none");
+            return;
+        }
+
+        info!("Warnings:");
+        for &category in Category::ALL {
+            let count = self.counts[category as usize];
+            if count != 0 {
+                info!("  {:<24} {:>12}", category.name(), count);
+            }
+        }
+    }
+
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let counts = Category::ALL
+            .iter()
+            .map(|&category| {
+                let count = self.counts[category as usize];
+                (category.name().to_string(), Value::from(count))
+            })
+            .collect::<Map<_, _>>();
+
+        let file = File::create(path).context("could not create warnings file")?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &counts)
+            .context("could not write warnings file")
+    }
+}
@@ -0,0 +1,80 @@
+//! Reads the output of `--format arrow-ipc` back with `arrow-ipc`.
+
+mod common;
+
+use arrow_array::{Array, RecordBatch, StringArray};
+use arrow_ipc::reader::StreamReader;
+use common::convert;
+
+/// One row of the triple table, with the columns in schema order.
+type Row = [Option<String>; 6];
+
+fn read_rows(buf: &[u8]) -> Vec<Row> {
+    let reader = StreamReader::try_new(buf, None).unwrap();
+    let mut rows = Vec::new();
+    for batch in reader {
+        let batch: RecordBatch = batch.unwrap();
+        let columns: Vec<&StringArray> = (0..6)
+            .map(|i| batch.column(i).as_any().downcast_ref().unwrap())
+            .collect();
+        for row in 0..batch.num_rows() {
+            let value = |i: usize| {
+                let column = columns[i];
+                Some(column.value(row).to_string()).filter(|_| column.is_valid(row))
+            };
+            rows.push([value(0), value(1), value(2), value(3), value(4), value(5)]);
+        }
+    }
+    rows
+}
+
+fn row<'a>(rows: &'a [Row], subject: &str, predicate: &str) -> &'a Row {
+    rows.iter()
+        .find(|row| row[0].as_deref() == Some(subject) && row[1].as_deref() == Some(predicate))
+        .unwrap()
+}
+
+#[test]
+fn keeps_the_kind_datatype_and_language_of_objects() {
+    let output = convert("dump", &["--format", "arrow-ipc", "--lang", "en"]);
+    assert!(output.status.success());
+
+    let rows = read_rows(&output.stdout);
+    let reputation = row(&rows, "u3", "user.reputation");
+    assert_eq!(reputation[2].as_deref(), Some("15000"));
+    assert_eq!(reputation[3].as_deref(), Some("literal"));
+    assert_eq!(
+        reputation[4].as_deref(),
+        Some("http://www.w3.org/2001/XMLSchema#integer")
+    );
+    assert_eq!(reputation[5], None);
+
+    let user = row(&rows, "b1", "badge.user");
+    assert_eq!(user[2].as_deref(), Some("u3"));
+    assert_eq!(user[3].as_deref(), Some("blank"));
+    assert_eq!(user[4], None);
+
+    let about_me = row(&rows, "u3", "user.about_me");
+    assert_eq!(about_me[3].as_deref(), Some("literal"));
+    assert_eq!(about_me[5].as_deref(), Some("en"));
+}
+
+#[test]
+fn writes_named_nodes_as_iris() {
+    let output = convert(
+        "dump",
+        &["--format", "arrow-ipc", "--base-iri", "https://so.example/"],
+    );
+    assert!(output.status.success());
+
+    let rows = read_rows(&output.stdout);
+    let user = rows
+        .iter()
+        .find(|row| row[1].as_deref() == Some("badge.user"))
+        .unwrap();
+    assert_eq!(user[3].as_deref(), Some("iri"));
+    assert!(user[2]
+        .as_deref()
+        .unwrap()
+        .starts_with("https://so.example/"));
+}
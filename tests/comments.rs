@@ -0,0 +1,22 @@
+mod common;
+
+use common::{integer, objects, triples};
+
+#[test]
+fn converts_comments_without_content_license() {
+    let triples = triples("old-comments", &[]);
+
+    assert_eq!(objects(&triples, "_:c1", "comment.post"), ["_:p2"]);
+    assert_eq!(objects(&triples, "_:c1", "comment.score"), [integer(3)]);
+    assert_eq!(
+        objects(&triples, "_:c1", "comment.text"),
+        ["\"Predates licenses\""]
+    );
+    assert_eq!(objects(&triples, "_:c1", "comment.user"), ["_:u4"]);
+    assert!(objects(&triples, "_:c1", "comment.content_license").is_empty());
+
+    assert_eq!(
+        objects(&triples, "_:c2", "comment.content_license"),
+        ["\"CC BY-SA 4.0\""]
+    );
+}
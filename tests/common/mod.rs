@@ -0,0 +1,77 @@
+//! Helpers for the integration tests, which run the binary on the small dumps
+//! in `tests/fixtures`.
+
+// Every test file uses a different part of this module.
+#![allow(dead_code)]
+
+use flate2::read::MultiGzDecoder;
+
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The directory of a fixture dump, e.g. `dump`.
+pub fn fixture(name: &str) -> PathBuf {
+    [env!("CARGO_MANIFEST_DIR"), "tests", "fixtures", name]
+        .iter()
+        .collect()
+}
+
+/// Converts a fixture with `args` added to the command line. The output file
+/// is decompressed into the `stdout` of the returned output.
+pub fn convert(name: &str, args: &[&str]) -> Output {
+    static RUNS: AtomicUsize = AtomicUsize::new(0);
+    let run = RUNS.fetch_add(1, Ordering::Relaxed);
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!(
+        "{}-{}-{}.gz",
+        name,
+        std::process::id(),
+        run
+    ));
+
+    let mut output = Command::new(env!("CARGO_BIN_EXE_stackoverflow2rdf"))
+        .arg(fixture(name))
+        .arg(&path)
+        .args(args)
+        .output()
+        .unwrap();
+    if let Ok(file) = std::fs::File::open(&path) {
+        output.stdout.clear();
+        MultiGzDecoder::new(file)
+            .read_to_end(&mut output.stdout)
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+    output
+}
+
+/// Like `convert`, but fails the test unless the conversion succeeds, and
+/// returns the N-Triples that were written.
+pub fn triples(name: &str, args: &[&str]) -> String {
+    let output = convert(name, args);
+    assert!(
+        output.status.success(),
+        "conversion failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// The objects of the triples with this subject and predicate, where the
+/// predicate is relative to the default namespace, e.g. `post.score`.
+pub fn objects<'a>(triples: &'a str, subject: &str, predicate: &str) -> Vec<&'a str> {
+    let prefix = format!(
+        "{} <https://stackoverflow.com/schema#{}> ",
+        subject, predicate
+    );
+    triples
+        .lines()
+        .filter_map(|line| line.strip_prefix(&prefix)?.strip_suffix(" ."))
+        .collect()
+}
+
+/// An `xsd:integer` literal in N-Triples.
+pub fn integer(value: i64) -> String {
+    format!("\"{}\"^^<http://www.w3.org/2001/XMLSchema#integer>", value)
+}
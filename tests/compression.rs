@@ -0,0 +1,46 @@
+mod common;
+
+use common::fixture;
+use flate2::read::MultiGzDecoder;
+
+use std::io::Read;
+use std::process::Command;
+
+/// Converts the `aborted` fixture, whose PostHistory.xml has a record without
+/// a `PostId`, so that the run fails after the posts were written. Returns
+/// the compressed output.
+fn aborted_output(compression: &str) -> Vec<u8> {
+    let output = Command::new(env!("CARGO_BIN_EXE_stackoverflow2rdf"))
+        .arg(fixture("aborted"))
+        .arg("-")
+        .args(["--compression", compression, "--no-progress"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("`PostId` not found in attributes"));
+    output.stdout
+}
+
+/// The posts that were written before the run failed, in a complete stream.
+fn assert_has_posts(text: &str) {
+    assert!(text.ends_with(" .\n"));
+    assert!(text.contains("_:p5 <https://stackoverflow.com/schema#post.title>"));
+}
+
+#[test]
+fn aborted_gzip_output_decompresses() {
+    let compressed = aborted_output("gzip");
+    let mut text = String::new();
+    MultiGzDecoder::new(&compressed[..])
+        .read_to_string(&mut text)
+        .unwrap();
+    assert_has_posts(&text);
+}
+
+#[test]
+fn aborted_zstd_output_decompresses() {
+    let compressed = aborted_output("zstd");
+    let text = zstd::decode_all(&compressed[..]).unwrap();
+    assert_has_posts(&String::from_utf8(text).unwrap());
+}
@@ -0,0 +1,32 @@
+mod common;
+
+use common::convert;
+
+/// The error of a conversion that is expected to fail.
+fn error(entity: &str) -> String {
+    let output = convert("missing-attributes", &["--only", entity]);
+    assert!(!output.status.success());
+    String::from_utf8(output.stderr).unwrap()
+}
+
+#[test]
+fn names_the_missing_attribute() {
+    assert!(error("posthistory").contains("`PostId` not found in attributes"));
+    assert!(error("users").contains("`DownVotes` not found in attributes"));
+}
+
+#[test]
+fn rejects_durations_that_overflow() {
+    let output = convert("dump", &["--timeout", "9999999999999999h"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("duration too large"));
+}
+
+#[test]
+fn names_the_position_of_a_malformed_record() {
+    let output = convert("repeated-attribute", &["--only", "users"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Users: record at position 235"));
+}
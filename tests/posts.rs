@@ -0,0 +1,52 @@
+mod common;
+
+use common::{convert, objects, triples};
+
+#[test]
+fn writes_view_buckets() {
+    let triples = triples("dump", &["--view-buckets"]);
+
+    assert_eq!(
+        objects(&triples, "_:p1", "post.view_bucket"),
+        ["\"1k-10k\""]
+    );
+    assert_eq!(objects(&triples, "_:p4", "post.view_bucket"), ["\"<100\""]);
+    assert_eq!(objects(&triples, "_:p5", "post.view_bucket"), ["\">10k\""]);
+    // Answers have no view count.
+    assert!(objects(&triples, "_:p2", "post.view_bucket").is_empty());
+}
+
+#[test]
+fn skips_deleted_posts() {
+    // Post 5 has a `DeletionDate`, post 1 does not.
+    let all = triples("dump", &["--only", "posts"]);
+    assert!(all.lines().any(|line| line.starts_with("_:p5 ")));
+
+    let live = triples("dump", &["--only", "posts", "--skip-deleted"]);
+    assert!(!live.lines().any(|line| line.starts_with("_:p5 ")));
+    assert_eq!(objects(&live, "_:p1", "post.title"), ["\"How to x\""]);
+}
+
+#[test]
+fn links_editors_of_posts_whose_owner_is_not_a_user() {
+    // The owner of post 1, user 9, is not in Users.xml.
+    let triples = triples("unknown-owner", &["--editor-edges"]);
+
+    assert_eq!(objects(&triples, "_:p1", "post.owner"), ["_:u9"]);
+    assert_eq!(objects(&triples, "_:u3", "user.edited"), ["_:p1"]);
+    assert!(objects(&triples, "_:u9", "user.edited").is_empty());
+}
+
+#[test]
+fn skips_edits_of_posts_without_a_known_owner() {
+    // Post 2 has no `OwnerUserId` and post 7 is not in Posts.xml.
+    let output = convert("unknown-owner", &["--editor-edges"]);
+    assert!(output.status.success());
+    let triples = String::from_utf8(output.stdout).unwrap();
+    assert!(!objects(&triples, "_:u3", "user.edited").contains(&"_:p2"));
+    assert!(!objects(&triples, "_:u3", "user.edited").contains(&"_:p7"));
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("h202: owner of post 2 is unknown, writing no `user.edited`"));
+    assert!(stderr.contains("h203: owner of post 7 is unknown, writing no `user.edited`"));
+}
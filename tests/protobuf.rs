@@ -0,0 +1,77 @@
+//! Reads the output of `--format protobuf` the way a custom loader would,
+//! with the messages `prost-build` generates from `res/stackoverflow.proto`.
+
+mod common;
+
+use common::convert;
+use prost::Message;
+
+#[allow(clippy::large_enum_variant)]
+mod proto {
+    include!(concat!(env!("OUT_DIR"), "/stackoverflow2rdf.rs"));
+}
+
+use proto::record::Entity;
+use proto::{Badge, Record, User};
+
+/// Decodes a stream of length-delimited records.
+fn read_entities(mut buf: &[u8]) -> Vec<Entity> {
+    let mut entities = Vec::new();
+    while !buf.is_empty() {
+        let record = Record::decode_length_delimited(&mut buf).unwrap();
+        entities.push(record.entity.unwrap());
+    }
+    entities
+}
+
+fn badges(entities: &[Entity]) -> Vec<&Badge> {
+    entities
+        .iter()
+        .filter_map(|entity| match entity {
+            Entity::Badge(badge) => Some(badge),
+            _ => None,
+        })
+        .collect()
+}
+
+fn users(entities: &[Entity]) -> Vec<&User> {
+    entities
+        .iter()
+        .filter_map(|entity| match entity {
+            Entity::User(user) => Some(user),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn reads_one_message_per_entity() {
+    let output = convert("dump", &["--format", "protobuf"]);
+    assert!(output.status.success());
+
+    let entities = read_entities(&output.stdout);
+    let badges = badges(&entities);
+    assert_eq!(badges.len(), 2);
+    let users = users(&entities);
+    assert_eq!(users.len(), 3);
+
+    let badge = badges.iter().find(|badge| badge.id == "b1").unwrap();
+    assert_eq!(badge.user.as_deref(), Some("u3"));
+    let user = users.iter().find(|user| user.id == "u3").unwrap();
+    assert_eq!(user.display_name.as_deref(), Some("Alice"));
+    assert_eq!(user.reputation, Some(15000));
+    assert!(user.other.is_empty());
+}
+
+#[test]
+fn keeps_triples_without_a_field_in_other() {
+    let output = convert("dump", &["--format", "protobuf", "--emit-types"]);
+    assert!(output.status.success());
+
+    let entities = read_entities(&output.stdout);
+    let users = users(&entities);
+    let user = users.iter().find(|user| user.id == "u3").unwrap();
+    assert_eq!(user.display_name.as_deref(), Some("Alice"));
+    assert_eq!(user.other.len(), 1);
+    assert!(user.other[0].predicate.ends_with("#type"));
+}
@@ -0,0 +1,80 @@
+//! Checks that `stackoverflow2rdf schema` lists the predicates that are
+//! actually written, since the write functions name their predicates
+//! themselves.
+
+mod common;
+
+use common::triples;
+use serde_json::Value;
+
+use std::collections::HashMap;
+use std::process::Command;
+
+/// The kind of every predicate of `schema --json`, `literal` or `relation`.
+fn schema() -> HashMap<String, String> {
+    let output = Command::new(env!("CARGO_BIN_EXE_stackoverflow2rdf"))
+        .args(["schema", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let predicates: Vec<Value> = serde_json::from_slice(&output.stdout).unwrap();
+    predicates
+        .iter()
+        .map(|predicate| {
+            let name = predicate["predicate"].as_str().unwrap().to_string();
+            let kind = predicate["kind"].as_str().unwrap().to_string();
+            (name, kind)
+        })
+        .collect()
+}
+
+#[test]
+fn lists_every_predicate_that_is_written() {
+    let schema = schema();
+    let runs: &[&[&str]] = &[
+        &[
+            "--about-me-text",
+            "--answer-rank",
+            "--editor-edges",
+            "--emit-inverse",
+            "--emit-year",
+            "--tag-cooccurrence",
+            "--cooccurrence-min-weight",
+            "1",
+            "--user-badge-edges",
+            "--view-buckets",
+            "--content-hash",
+            "sha256",
+        ],
+        &["--inline-tags", "--reify", "all"],
+        &["--output-stats-only"],
+    ];
+
+    for args in runs {
+        for line in triples("dump", args).lines() {
+            let mut parts = line.splitn(3, ' ');
+            let (_, predicate, object) =
+                (parts.next(), parts.next().unwrap(), parts.next().unwrap());
+            let predicate = match predicate
+                .strip_prefix("<https://stackoverflow.com/schema#")
+                .and_then(|predicate| predicate.strip_suffix('>'))
+            {
+                Some(predicate) => predicate,
+                // e.g. `rdf:type`.
+                None => continue,
+            };
+            let kind = schema
+                .get(predicate)
+                .unwrap_or_else(|| panic!("{} is written, but not in the schema", predicate));
+            let is_node = object.starts_with("_:");
+            assert_eq!(
+                kind == "relation",
+                is_node,
+                "{} is a {} in the schema, but written as {}",
+                predicate,
+                kind,
+                object
+            );
+        }
+    }
+}
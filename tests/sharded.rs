@@ -0,0 +1,16 @@
+mod common;
+
+use common::{integer, objects, triples};
+
+#[test]
+fn reads_every_shard_in_numeric_order() {
+    let triples = triples("shards", &[]);
+
+    let posts = triples
+        .lines()
+        .filter(|line| line.contains("#post.score>"))
+        .map(|line| line.split(' ').next().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(posts, ["_:p1", "_:p2", "_:p10"]);
+    assert_eq!(objects(&triples, "_:p10", "post.score"), [integer(10)]);
+}
@@ -0,0 +1,37 @@
+mod common;
+
+use common::{convert, objects, triples};
+
+#[test]
+fn writes_excerpt_and_wiki_posts() {
+    let triples = triples("dump", &[]);
+
+    // The id of `rust`, whose name is base32 encoded.
+    let rust = "_:tOJ2XG5A";
+    assert_eq!(objects(&triples, rust, "tag.excerpt_post"), ["_:p6"]);
+    assert_eq!(objects(&triples, rust, "tag.wiki_post"), ["_:p7"]);
+}
+
+#[test]
+fn splits_post_tags() {
+    let triples = triples("tags", &["--readable-tag-ids"]);
+
+    assert!(objects(&triples, "_:p1", "post.tags").is_empty());
+    assert_eq!(objects(&triples, "_:p2", "post.tags"), ["_:trust"]);
+    // `<c++><r&d>`, written with character and entity references.
+    assert_eq!(
+        objects(&triples, "_:p3", "post.tags"),
+        ["_:tc.2B.2B", "_:tr.26d"]
+    );
+    // A value that is not a sequence of `<name>`s gets no tags at all.
+    assert!(objects(&triples, "_:p4", "post.tags").is_empty());
+    assert_eq!(objects(&triples, "_:p4", "post.title"), ["\"Malformed\""]);
+}
+
+#[test]
+fn warns_about_malformed_tags() {
+    let output = convert("tags", &[]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("p4: invalid `post.tags` \"<rust><py\", writing no tags"));
+}
@@ -0,0 +1,11 @@
+mod common;
+
+use common::{integer, objects, triples};
+
+#[test]
+fn writes_upvotes_and_downvotes() {
+    let triples = triples("dump", &[]);
+
+    assert_eq!(objects(&triples, "_:u3", "user.upvotes"), [integer(10)]);
+    assert_eq!(objects(&triples, "_:u3", "user.downvotes"), [integer(2)]);
+}